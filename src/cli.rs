@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use crate::*;
+
+/*
+    The command line of the snake binary. `main` parses it before anything else and
+    hands it to the app as a resource, the plugins then read it while they are built,
+    and anything given here wins over the settings file.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LaunchArgs {
+    pub seed: Option<u64>,
+    //a settings file to use instead of the one in the config dir
+    pub config: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub mode: Option<GameMode>,
+    //ticks per second at the start of a run
+    pub tick_rate: Option<f64>,
+    pub headless: bool,
+    pub skip_menu: bool,
+    pub replay: Option<PathBuf>,
+}
+
+pub const USAGE: &str = "\
+Usage: snake [options]
+
+Options:
+    --seed <number>      play the run with this seed
+    --config <path>      read the settings from this file
+    --data-dir <path>    keep settings and saves in this directory
+    --mode <mode>        Classic or Wrap
+    --tick-rate <rate>   moves per second at the start of a run
+    --headless           run without a window, straight into a game
+    --skip-menu          go straight into a game
+    --replay <file>      play back a recorded run
+    --help               print this and quit";
+
+impl LaunchArgs {
+    //prints what went wrong and quits on bad arguments, like any other command line tool
+    pub fn from_env() -> Self {
+        match LaunchArgs::parse(std::env::args().skip(1)) {
+            Ok(Some(args)) => args,
+            Ok(None) => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            Err(error) => {
+                eprintln!("{error}\n\n{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    //`None` when help was asked for
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>, String> {
+        let mut launch_args = LaunchArgs::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = value()?;
+                    launch_args.seed = Some(seed.parse().map_err(|_| format!("--seed: {seed} is not a whole number"))?);
+                }
+                "--config" => launch_args.config = Some(PathBuf::from(value()?)),
+                "--data-dir" => launch_args.data_dir = Some(PathBuf::from(value()?)),
+                "--mode" => {
+                    let mode = value()?;
+                    launch_args.mode = Some(parse_mode(&mode).ok_or(format!("--mode: {mode} is not Classic or Wrap"))?);
+                }
+                "--tick-rate" => {
+                    let rate = value()?;
+                    let tick_rate: f64 = rate.parse().map_err(|_| format!("--tick-rate: {rate} is not a number"))?;
+                    if !(tick_rate.is_finite() && tick_rate > 0.) {
+                        return Err(format!("--tick-rate: {rate} has to be positive"));
+                    }
+                    launch_args.tick_rate = Some(tick_rate);
+                }
+                //there is no one to click through the menu without a window
+                "--headless" => {
+                    launch_args.headless = true;
+                    launch_args.skip_menu = true;
+                }
+                "--skip-menu" => launch_args.skip_menu = true,
+                "--replay" => launch_args.replay = Some(PathBuf::from(value()?)),
+                "--help" | "-h" => return Ok(None),
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+
+        Ok(Some(launch_args))
+    }
+
+    //puts the flags that have a settings counterpart over the settings from the file
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(seed) = self.seed {
+            settings.seed = Some(seed);
+        }
+        if let Some(mode) = self.mode {
+            settings.mode = mode;
+        }
+        if let Some(tick_rate) = self.tick_rate {
+            settings.tick_interval = Some(1. / tick_rate);
+        }
+    }
+}
+
+fn parse_mode(mode: &str) -> Option<GameMode> {
+    [GameMode::Classic, GameMode::Wrap].into_iter()
+        .find(|candidate| candidate.name().eq_ignore_ascii_case(mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<LaunchArgs>, String> {
+        LaunchArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn seed_is_a_whole_number() {
+        assert_eq!(parse(&["--seed", "1234"]).unwrap().unwrap().seed, Some(1234));
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--seed", "abc"]).is_err());
+        assert_eq!(parse(&["--seed"]), Err("--seed needs a value".to_string()));
+    }
+
+    #[test]
+    fn tick_rate_becomes_the_interval() {
+        let args = parse(&["--tick-rate", "20"]).unwrap().unwrap();
+        assert_eq!(args.tick_rate, Some(20.));
+
+        let mut settings = Settings::default();
+        args.apply(&mut settings);
+        assert_eq!(settings.tick_interval, Some(0.05));
+    }
+
+    #[test]
+    fn tick_rate_has_to_be_positive() {
+        assert!(parse(&["--tick-rate", "0"]).is_err());
+        assert!(parse(&["--tick-rate", "-5"]).is_err());
+        assert!(parse(&["--tick-rate", "inf"]).is_err());
+        assert!(parse(&["--tick-rate", "fast"]).is_err());
+    }
+
+    #[test]
+    fn headless_skips_the_menu() {
+        let args = parse(&["--headless"]).unwrap().unwrap();
+        assert!(args.headless && args.skip_menu);
+
+        let args = parse(&["--skip-menu"]).unwrap().unwrap();
+        assert!(!args.headless && args.skip_menu);
+    }
+
+    #[test]
+    fn unknown_flags_are_refused() {
+        assert_eq!(parse(&["--sed", "1"]), Err("unknown argument --sed".to_string()));
+        assert!(parse(&["--mode", "Maze"]).is_err());
+        assert_eq!(parse(&["--mode", "wrap"]).unwrap().unwrap().mode, Some(GameMode::Wrap));
+    }
+
+    #[test]
+    fn help_asks_for_no_game() {
+        assert_eq!(parse(&["--seed", "1", "--help"]), Ok(None));
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/*
+    The settings file. Every field has a default, so a file that leaves some out still
+    loads, and a file that is missing or broken never stops the game from starting.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Settings {
+    //older layouts are upgraded by `migrate::settings` before they get here
+    pub(crate) schema_version: u32,
+    pub(crate) game_name: String,
+    pub(crate) window_size: WindowSize,
+    pub(crate) resizable: bool,
+    pub(crate) tile_size: i32,
+    pub(crate) arena_size: ArenaSize,
+    pub(crate) mode: GameMode,
+    pub(crate) difficulty: Difficulty,
+    //seconds per tick at the start of a run, replaces the one from the difficulty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tick_interval: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) seed: Option<u64>,
+    //race a see-through copy of the best recorded run of the mode
+    pub(crate) ghost: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: migrate::settings::CHAIN.len() as u32,
+            game_name: "Snake".to_string(),
+            window_size: WindowSize::default(),
+            resizable: false,
+            tile_size: 16,
+            arena_size: ArenaSize::default(),
+            mode: GameMode::default(),
+            difficulty: Difficulty::default(),
+            tick_interval: None,
+            seed: None,
+            ghost: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct WindowSize {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+impl Default for WindowSize {
+    fn default() -> Self {
+        WindowSize { width: 532., height: 532. }
+    }
+}
+
+//in tiles, not pixels, so the tile size always divides the arena evenly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ArenaSize {
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+}
+
+impl Default for ArenaSize {
+    fn default() -> Self {
+        ArenaSize { width: 30, height: 30 }
+    }
+}
+
+//the smallest board the starting snake fits on with room to turn
+pub(crate) const MIN_ARENA_TILES: i32 = 3;
+
+//a problem with one field of the settings file, named by its path like `WindowSize.Width`
+#[derive(Debug, Clone)]
+pub struct SettingsError {
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.field.is_empty() || self.field == "." {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+impl SettingsError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        SettingsError { field: field.to_string(), message: message.into() }
+    }
+}
+
+//a field that parsed but holds a value the game cannot run with, see `Settings::validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidSetting {
+    WindowWidth,
+    WindowHeight,
+    TileSize,
+    ArenaWidth,
+    ArenaHeight,
+    TickInterval,
+}
+
+impl InvalidSetting {
+    //the path of the field in the settings file
+    pub fn field(self) -> &'static str {
+        match self {
+            InvalidSetting::WindowWidth => "WindowSize.Width",
+            InvalidSetting::WindowHeight => "WindowSize.Height",
+            InvalidSetting::TileSize => "TileSize",
+            InvalidSetting::ArenaWidth => "ArenaSize.Width",
+            InvalidSetting::ArenaHeight => "ArenaSize.Height",
+            InvalidSetting::TickInterval => "TickInterval",
+        }
+    }
+}
+
+impl fmt::Display for InvalidSetting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            InvalidSetting::WindowWidth | InvalidSetting::WindowHeight | InvalidSetting::TileSize => "has to be positive".to_string(),
+            InvalidSetting::ArenaWidth | InvalidSetting::ArenaHeight => format!("has to be at least {MIN_ARENA_TILES} tiles"),
+            InvalidSetting::TickInterval => "has to be a positive number of seconds".to_string(),
+        };
+        write!(f, "{}: {message}", self.field())
+    }
+}
+
+impl Settings {
+    /*
+        Loads the settings, never failing. A missing file is filled in from `defaults`,
+        the read-only settings shipped with the game, a file that does not parse is
+        ignored as a whole, and single fields with values that make no sense are put back
+        to their default. Problems are printed since logging is not set up yet while the
+        app is being built. Only the settings file in the config dir is written when it is
+        missing, `write_missing` is false for a file the player pointed at with `--config`.
+     */
+    pub fn load(path: &Path, defaults: &Path, write_missing: bool) -> Settings {
+        if !path.exists() {
+            let settings = Settings::load_defaults(defaults);
+            if !write_missing {
+                eprintln!("{}: not found, using the default settings", path.display());
+                return settings;
+            }
+
+            match settings.save(path) {
+                Ok(()) => eprintln!("{}: not found, wrote the default settings", path.display()),
+                Err(error) => eprintln!("{}: not found and could not write the default settings: {error}", path.display()),
+            }
+            return settings;
+        }
+
+        Settings::load_from(path).unwrap_or_else(|| Settings::load_defaults(defaults))
+    }
+
+    //the shipped settings, or the built in ones when those are missing too
+    fn load_defaults(defaults: &Path) -> Settings {
+        if !defaults.exists() {
+            return Settings::default();
+        }
+
+        Settings::load_from(defaults).unwrap_or_default()
+    }
+
+    fn load_from(path: &Path) -> Option<Settings> {
+        let mut settings = match Settings::read(path) {
+            Ok(settings) => settings,
+            Err(error) => {
+                eprintln!("{}: {error}, using the default settings", path.display());
+                return None;
+            }
+        };
+
+        for error in settings.fix() {
+            eprintln!("{}: {error}, using the default", path.display());
+        }
+
+        Some(settings)
+    }
+
+    pub fn read(path: &Path) -> Result<Settings, SettingsError> {
+        let file = File::open(path).map_err(|error| SettingsError::new("", error.to_string()))?;
+        let mut value: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| SettingsError::new("", error.to_string()))?;
+        let version = migrate::migrate(&mut value, migrate::settings::CHAIN)
+            .map_err(|error| SettingsError::new("", error))?;
+        //the settings file is only ever written when it is missing, so a newer one is safe to read
+        if version > migrate::settings::CHAIN.len() as u32 {
+            eprintln!("{}: written by a newer version of the game (schema {version}), loading what it can", path.display());
+        }
+
+        serde_path_to_error::deserialize(value)
+            .map_err(|error| SettingsError::new(&error.path().to_string(), error.inner().to_string()))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::from)?;
+        std::fs::write(path, json)
+    }
+
+    //every field holding a value the game cannot run with
+    pub fn validate(&self) -> Vec<InvalidSetting> {
+        let mut errors = Vec::new();
+
+        if !is_positive(self.window_size.width as f64) {
+            errors.push(InvalidSetting::WindowWidth);
+        }
+        if !is_positive(self.window_size.height as f64) {
+            errors.push(InvalidSetting::WindowHeight);
+        }
+        if self.tile_size <= 0 {
+            errors.push(InvalidSetting::TileSize);
+        }
+        if self.arena_size.width < MIN_ARENA_TILES {
+            errors.push(InvalidSetting::ArenaWidth);
+        }
+        if self.arena_size.height < MIN_ARENA_TILES {
+            errors.push(InvalidSetting::ArenaHeight);
+        }
+        if self.tick_interval.is_some_and(|interval| !is_positive(interval)) {
+            errors.push(InvalidSetting::TickInterval);
+        }
+
+        errors
+    }
+
+    //puts every invalid field back to its default and returns what was wrong
+    fn fix(&mut self) -> Vec<InvalidSetting> {
+        let errors = self.validate();
+        let default = Settings::default();
+
+        for error in errors.iter() {
+            match error {
+                InvalidSetting::WindowWidth => self.window_size.width = default.window_size.width,
+                InvalidSetting::WindowHeight => self.window_size.height = default.window_size.height,
+                InvalidSetting::TileSize => self.tile_size = default.tile_size,
+                InvalidSetting::ArenaWidth => self.arena_size.width = default.arena_size.width,
+                InvalidSetting::ArenaHeight => self.arena_size.height = default.arena_size.height,
+                InvalidSetting::TickInterval => self.tick_interval = default.tick_interval,
+            }
+        }
+
+        errors
+    }
+}
+
+fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Settings::default().validate().is_empty());
+    }
+
+    #[test]
+    fn validate_names_every_bad_field() {
+        let mut settings = Settings::default();
+        settings.window_size.height = f32::NAN;
+        settings.tile_size = 0;
+        settings.arena_size.width = MIN_ARENA_TILES - 1;
+        settings.tick_interval = Some(-0.1);
+
+        assert_eq!(settings.validate(), vec![
+            InvalidSetting::WindowHeight,
+            InvalidSetting::TileSize,
+            InvalidSetting::ArenaWidth,
+            InvalidSetting::TickInterval,
+        ]);
+        assert_eq!(InvalidSetting::ArenaWidth.to_string(), "ArenaSize.Width: has to be at least 3 tiles");
+    }
+
+    #[test]
+    fn fix_only_touches_the_bad_fields() {
+        let mut settings = Settings {
+            tile_size: -4,
+            arena_size: ArenaSize { width: 1, height: 12 },
+            tick_interval: Some(0.2),
+            ..Default::default()
+        };
+
+        assert_eq!(settings.fix().len(), 2);
+        assert!(settings.validate().is_empty());
+        assert_eq!(settings.tile_size, 16);
+        assert_eq!((settings.arena_size.width, settings.arena_size.height), (30, 12));
+        assert_eq!(settings.tick_interval, Some(0.2));
+    }
+
+    #[test]
+    fn bad_fields_in_a_file_fall_back() {
+        let dir = TempDir::new("settings-bad-field");
+        let path = dir.join("settings.json");
+        std::fs::write(&path, r#"{"SchemaVersion":1,"TileSize":0,"Mode":"Wrap"}"#).unwrap();
+
+        let settings = Settings::load(&path, &dir.join("missing.json"), true);
+        assert_eq!(settings.tile_size, 16);
+        assert_eq!(settings.mode, GameMode::Wrap);
+    }
+
+    #[test]
+    fn only_the_config_dir_file_is_written_when_missing() {
+        let dir = TempDir::new("settings-missing");
+        let defaults = dir.join("missing.json");
+
+        let given = dir.join("given.json");
+        Settings::load(&given, &defaults, false);
+        assert!(!given.exists());
+
+        let own = dir.join("settings.json");
+        Settings::load(&own, &defaults, true);
+        assert!(Settings::read(&own).is_ok());
+    }
+}
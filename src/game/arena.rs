@@ -1,30 +1,69 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, sprite::Anchor};
 
 use crate::*;
+use crate::game::*;
 
 #[derive(Component)]
 pub struct Arena;
 
-pub const ARENA_SIZE: f32 = 480.;
+//size of the board in tiles and of a tile in pixels, read from the settings file
+#[derive(Copy, Clone, Debug)]
+pub struct ArenaConfig {
+    pub width: i32,
+    pub height: i32,
+    pub tile_size: f32,
+}
+
+impl ArenaConfig {
+    pub fn pixel_width(&self) -> f32 {
+        self.width as f32 * self.tile_size
+    }
+
+    pub fn pixel_height(&self) -> f32 {
+        self.height as f32 * self.tile_size
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.pixel_width() / 2., self.pixel_height() / 2.)
+    }
+
+    pub fn tile(&self) -> Vec2 {
+        Vec2::splat(self.tile_size)
+    }
+
+    //sprites are anchored top left, so a cell is drawn one tile above its row
+    pub fn cell_to_translation(&self, cell: world::Cell, z: f32) -> Vec3 {
+        Vec3::new(cell.x as f32 * self.tile_size, (cell.y + 1) as f32 * self.tile_size, z)
+    }
+}
+
+//the sprite layer for the snake and food, above the board tiles
+pub const SPRITE_Z: f32 = 1.;
 
 pub fn setup(
     mut commands: Commands,
     textures: Res<resources::GameTextures>, 
-    //settings: Res<Settings>, 
+    arena: Res<ArenaConfig>,
 ) {
-    commands.spawn_bundle(SpriteBundle {
-        texture: textures.checkerboard.clone(),
-        sprite: Sprite {
-            //anchor: bevy::sprite::Anchor::TopLeft,
-            custom_size: Some(Vec2::new(480.,480.)),
-            ..default()
-        },
-        transform: Transform {
-            translation: Vec3::new(240.,240.,0.),
-            ..default()
-        },
-        ..default()
-    })
-    .insert(Arena)
-    .insert(game::GameTag);
-}
\ No newline at end of file
+    //one checkerboard tile per cell so any board size and tile size lines up
+    for y in 0..arena.height {
+        for x in 0..arena.width {
+            commands.spawn_bundle(SpriteSheetBundle {
+                texture_atlas: textures.checkerboard.clone(),
+                sprite: TextureAtlasSprite {
+                    index: ((x + y) % 2) as usize,
+                    anchor: Anchor::TopLeft,
+                    custom_size: Some(arena.tile()),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: arena.cell_to_translation(world::Cell::new(x, y), 0.),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Arena)
+            .insert(game::GameTag);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
 use crate::*;
+use crate::game::{highscore, killcam, leaderboard};
+use crate::shared::text_input::{text_input_sections, TextInput, TextSubmitted};
 
 #[derive(Component)]
 pub enum ButtonAction {
@@ -11,33 +13,31 @@ pub enum ButtonAction {
 #[derive(Component)]
 pub struct DeathScreenTag;
 
+//the field the name for the leaderboard is typed into
+#[derive(Component)]
+pub struct NameEntry;
+
+#[derive(Component)]
+pub struct NamePrompt;
+
 pub fn setup(
     mut commands: Commands,
     font_assets: Res<resources::GameFonts>,
+    last_run: Res<game::highscore::LastRun>,
+    player_data: Res<PlayerData>,
 ) { 
-    let button_style: Style = Style {
-        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
-        margin: Rect::all(Val::Px(8.)),
-        padding: Rect::all(Val::Px(8.)),
-        align_content: AlignContent::Center,
-        align_items: AlignItems::Center,
-        align_self: AlignSelf::Center,
-        justify_content: JustifyContent::Center,
-        ..Default::default()
-    };
-
-    let button_txt_style : TextStyle = TextStyle {
-        font: font_assets.bold.clone(),
-        font_size: 20.0,
-        color: Color::rgb(0.2, 0.2, 0.9),
-    };
-
     let title_txt_style : TextStyle = TextStyle {
         font: font_assets.bold.clone(),
         font_size: 40.0,
         color: Color::rgb(1., 1., 1.),
     };
 
+    let cause_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 16.0,
+        color: Color::rgb(0.8, 0.8, 0.8),
+    };
+
     let container = commands
     .spawn_bundle(NodeBundle {
         style: Style {
@@ -71,6 +71,96 @@ pub fn setup(
     })
     .insert(Parent(container));
 
+    if let Some(death) = last_run.death {
+        commands.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: death_string(&death),
+                    style: cause_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container));
+    }
+
+    spawn_rank(&mut commands, container, &font_assets, &last_run, &player_data);
+
+    //the buttons wait for the killcam, `show_buttons` puts them up once it is done
+}
+
+//what killed the snake, where and when, the cell is the one the head was on
+fn death_string(death: &KillSnake) -> String {
+    let cause = match death.cause {
+        DeathCause::Wall(side) => format!("HIT THE {} WALL", side.name().to_uppercase()),
+        DeathCause::OwnBody(segment) => format!("RAN INTO ITS OWN BODY, SEGMENT {segment}"),
+        DeathCause::Obstacle => "HIT AN OBSTACLE".to_string(),
+        DeathCause::OtherSnake => "RAN INTO ANOTHER SNAKE".to_string(),
+        DeathCause::Timeout => "RAN OUT OF TIME".to_string(),
+    };
+
+    format!("{cause} AT {}, {} ON TICK {}", death.cell.x, death.cell.y, death.tick)
+}
+
+//the place the run got on the leaderboard and a field for the name to put there, nothing when it did not make it
+pub(crate) fn spawn_rank(
+    commands: &mut Commands,
+    container: Entity,
+    font_assets: &resources::GameFonts,
+    last_run: &highscore::LastRun,
+    player_data: &PlayerData,
+) {
+    let rank = match last_run.rank {
+        Some(rank) => rank,
+        None => return,
+    };
+
+    let rank_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 20.0,
+        color: Color::rgb(1., 0.8, 0.2),
+    };
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: format!("NEW #{} ON THE LEADERBOARD", rank + 1),
+                style: rank_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    spawn_name_entry(commands, container, font_assets, player_data);
+}
+
+//the replay and menu buttons, the caller gives the screen they go on
+pub(crate) fn spawn_buttons(
+    commands: &mut Commands,
+    container: Entity,
+    font_assets: &resources::GameFonts,
+    config: &SnakeGameConfig,
+) {
+    let button_style: Style = Style {
+        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+        margin: Rect::all(Val::Px(8.)),
+        padding: Rect::all(Val::Px(8.)),
+        align_content: AlignContent::Center,
+        align_items: AlignItems::Center,
+        align_self: AlignSelf::Center,
+        justify_content: JustifyContent::Center,
+        ..Default::default()
+    };
+
+    let button_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 20.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
     commands.spawn_bundle(ButtonBundle {
         style: button_style.clone(),
         ..Default::default()
@@ -90,6 +180,9 @@ pub fn setup(
     .insert(Parent(container))
     .insert(ButtonAction::Replay);
 
+    //REPLAY is all the host gets, the rest is its own to offer
+    if !config.menus { return }
+
     commands.spawn_bundle(ButtonBundle {
         style: button_style.clone(),
         ..Default::default()
@@ -108,7 +201,108 @@ pub fn setup(
     })
     .insert(Parent(container))
     .insert(ButtonAction::Menu);
+}
+
+//the buttons of the death screen, they go up once the killcam has played
+pub fn show_buttons(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    config: Res<SnakeGameConfig>,
+    killcam: Res<killcam::Killcam>,
+    screen_query: Query<Entity, With<DeathScreenTag>>,
+    button_query: Query<(), With<ButtonAction>>,
+) {
+    if killcam.is_playing() || !button_query.is_empty() { return }
 
+    for container in screen_query.iter() {
+        spawn_buttons(&mut commands, container, &font_assets, &config);
+    }
+}
+
+//asks for a name for the run that just made the leaderboard, starting from the last one used
+fn spawn_name_entry(
+    commands: &mut Commands,
+    container: Entity,
+    font_assets: &resources::GameFonts,
+    player_data: &PlayerData,
+) {
+    let prompt_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.6, 0.6, 0.6),
+    };
+
+    let name_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 24.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "TYPE YOUR NAME, ENTER TO SAVE".to_string(),
+                style: prompt_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .insert(NamePrompt);
+
+    let text_input = TextInput::new(player_data.player_name(), leaderboard::MAX_NAME_LENGTH);
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: text_input_sections(&text_input, &name_txt_style, Color::rgb(1., 0.8, 0.2)),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .insert(text_input)
+    .insert(NameEntry);
+}
+
+//puts the typed name on the run and remembers it, an empty field keeps the name the run already has
+fn save_name(name: &str, player_data: &mut PlayerData, last_run: &highscore::LastRun) {
+    let name = name.trim();
+    if name.is_empty() { return }
+
+    if let Some(rank) = last_run.rank {
+        player_data.rename_run(last_run.mode, last_run.difficulty, rank, name);
+    }
+    player_data.set_player_name(name);
+}
+
+pub fn submit_name(
+    mut commands: Commands,
+    mut submitted_event: EventReader<TextSubmitted>,
+    mut name_query: Query<&mut Text, (With<NameEntry>, Without<NamePrompt>)>,
+    mut prompt_query: Query<&mut Text, With<NamePrompt>>,
+    mut player_data: ResMut<PlayerData>,
+    last_run: Res<highscore::LastRun>,
+) {
+    for event in submitted_event.iter() {
+        let mut text = match name_query.get_mut(event.entity) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if event.value.trim().is_empty() { continue }
+
+        save_name(&event.value, &mut player_data, &last_run);
+
+        //the field is done, the name stays without a cursor
+        commands.entity(event.entity).remove::<TextInput>();
+        text.sections[0].value = event.value.trim().to_string();
+        text.sections[1].value.clear();
+        text.sections[2].value.clear();
+
+        for mut prompt in prompt_query.iter_mut() {
+            prompt.sections[0].value = "SAVED".to_string();
+        }
+    }
 }
 
 pub fn button_effects(
@@ -130,8 +324,12 @@ pub fn button_effects(
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn button_update(
     interaction_query: Query<(&Interaction, &ButtonAction), (Changed<Interaction>, With<Button>)>,
+    name_query: Query<&TextInput, With<NameEntry>>,
+    mut player_data: ResMut<PlayerData>,
+    last_run: Res<highscore::LastRun>,
     mut game_state: ResMut<State<GameState>>,
     mut app_state: ResMut<State<AppState>>,
 ) {
@@ -145,6 +343,11 @@ pub fn button_update(
 
     for (interaction, menu_button_action) in interaction_query.iter() {
         if *interaction == Interaction::Clicked {
+            //leaving without pressing enter still keeps what was typed
+            for text_input in name_query.iter() {
+                save_name(&text_input.value(), &mut player_data, &last_run);
+            }
+
             match menu_button_action {
                 ButtonAction::Replay => replay_action(),
                 ButtonAction::Menu => menu_action()
@@ -0,0 +1,195 @@
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Insane => "Insane",
+        }
+    }
+
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Insane,
+            Difficulty::Insane => Difficulty::Easy,
+        }
+    }
+
+    pub fn pace(self) -> Pace {
+        match self {
+            Difficulty::Easy => Pace { interval: 0.15, speed_up: 0.99, min_interval: 0.08 },
+            Difficulty::Normal => Pace { interval: 0.1, speed_up: 0.98, min_interval: 0.05 },
+            Difficulty::Hard => Pace { interval: 0.075, speed_up: 0.97, min_interval: 0.04 },
+            Difficulty::Insane => Pace { interval: 0.05, speed_up: 0.97, min_interval: 0.025 },
+        }
+    }
+}
+
+//seconds between two movement ticks, and how much shorter that gets for every point scored
+#[derive(Copy, Clone, Debug)]
+pub struct Pace {
+    pub interval: f64,
+    pub speed_up: f64,
+    pub min_interval: f64,
+}
+
+pub struct TickClock {
+    pace: Pace,
+    interval: f64,
+    accumulator: f64,
+    //ticks run so far in this frame, `advance` is asked again after each one
+    ticks_this_frame: u32,
+    looping: bool,
+}
+
+//the most ticks one frame catches up on, time owed beyond that is dropped and the game slows down instead
+pub const MAX_TICKS_PER_FRAME: u32 = 16;
+
+impl Default for TickClock {
+    fn default() -> Self {
+        TickClock::new(Difficulty::default().pace())
+    }
+}
+
+impl TickClock {
+    pub fn new(pace: Pace) -> Self {
+        TickClock {
+            pace,
+            interval: pace.interval,
+            accumulator: 0.,
+            ticks_this_frame: 0,
+            looping: false,
+        }
+    }
+
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    pub fn set_score(&mut self, score: i32) {
+        let interval = self.pace.interval * self.pace.speed_up.powi(score);
+        self.interval = interval.max(self.pace.min_interval);
+    }
+
+    /*
+        Lets `seconds` go by, true when a tick is due. Meant to be called until it returns
+        false, like `FixedTimestep` does: the time of the frame is only put in on the first
+        call, every call after takes one more tick off it. A frame catches up on at most
+        `MAX_TICKS_PER_FRAME` ticks, so a long stall does not come back as a burst.
+     */
+    pub fn advance(&mut self, seconds: f64) -> bool {
+        if !self.looping {
+            self.accumulator += seconds;
+            self.ticks_this_frame = 0;
+        }
+
+        if self.accumulator >= self.interval && self.ticks_this_frame < MAX_TICKS_PER_FRAME {
+            self.accumulator -= self.interval;
+            self.ticks_this_frame += 1;
+            self.looping = true;
+            true
+        } else {
+            self.accumulator = self.accumulator.min(self.interval);
+            self.looping = false;
+            false
+        }
+    }
+
+    //no tick this frame, the next `advance` starts a new one
+    pub fn hold(&mut self) {
+        self.looping = false;
+    }
+}
+
+/*
+    Run criteria for the movement tick. Works like `FixedTimestep` but reads its step
+    from the `TickClock`, so the snake can speed up mid game. The tick set is run again
+    for every tick owed, each one going through all of its phases. Time only builds up
+    while a game is running, so nothing is owed when a new run starts, and once the
+    snake is dead or the board full no more ticks are run.
+ */
+pub fn run_on_tick(
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    mut clock: ResMut<TickClock>,
+    world: Option<Res<game::world::SnakeWorld>>,
+    playback: Option<ResMut<game::playback::Playback>>,
+) -> ShouldRun {
+    let world = match world {
+        Some(world) if world.is_alive() && !world.is_complete() => world,
+        _ => {
+            clock.hold();
+            return ShouldRun::No;
+        }
+    };
+    if game_state.current() != &GameState::InGame {
+        clock.hold();
+        return ShouldRun::No;
+    }
+
+    let mut delta = time.delta_seconds_f64();
+    if let Some(mut playback) = playback {
+        //a paused replay only moves when it is stepped, one tick at a time
+        if playback.is_paused() {
+            clock.hold();
+            return if playback.take_step() { ShouldRun::Yes } else { ShouldRun::No };
+        }
+        //the last tick of the recording is where it stops, `stop_at_end` pauses on it
+        if world.tick() >= playback.replay.end_tick() {
+            clock.hold();
+            return ShouldRun::No;
+        }
+        delta *= playback.speed();
+    }
+
+    if clock.advance(delta) { ShouldRun::YesAndCheckAgain } else { ShouldRun::No }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //how many ticks `advance` hands out for one frame
+    fn ticks(clock: &mut TickClock, seconds: f64) -> u32 {
+        let mut ticks = 0;
+        while clock.advance(seconds) {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn a_slow_frame_catches_up() {
+        let mut clock = TickClock::new(Difficulty::Insane.pace());
+        assert_eq!(ticks(&mut clock, 0.03), 0);
+        assert_eq!(ticks(&mut clock, 0.03), 1);
+        assert_eq!(ticks(&mut clock, 0.2), 4);
+    }
+
+    #[test]
+    fn catching_up_is_capped() {
+        let mut clock = TickClock::new(Difficulty::Normal.pace());
+        assert_eq!(ticks(&mut clock, 10.), MAX_TICKS_PER_FRAME);
+
+        //the rest is dropped, the next frame only gets its own time
+        assert_eq!(ticks(&mut clock, 0.), 1);
+        assert_eq!(ticks(&mut clock, 0.), 0);
+    }
+}
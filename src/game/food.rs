@@ -1,121 +1,78 @@
-use bevy::{prelude::*, sprite::Anchor, asset};
-use rand::Rng;
+use bevy::{prelude::*, sprite::Anchor};
 
 use crate::*;
 use crate::game::*;
+use crate::game::world::SnakeWorld;
 
 #[derive(Component)]
 pub struct Food;
 
+//sent when food has to be placed but every cell is already taken
+pub struct BoardFull;
+
 pub fn spawn(
     mut commands: Commands, 
     textures: Res<resources::GameTextures>,
-    settings: Res<Settings>, 
+    arena: Res<arena::ArenaConfig>,
 ) {
-    let width = (arena::ARENA_SIZE as i32) - 16;
-    let height = (arena::ARENA_SIZE as i32);;
-
-    let x: i32 = 16 * rand::thread_rng().gen_range(1..=(width/16));
-    let y: i32 = 16 * rand::thread_rng().gen_range(1..=(height/16));
-
+    //the sprite is placed by `update` once the world has a piece of food
     let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
         texture_atlas: textures.snake_atlas.clone(),
         sprite: TextureAtlasSprite {
             index: 2,
             anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
             ..default()
         },
-        transform: Transform {
-            translation: new_food_pos(),
-            ..default()
-        },
+        visibility: Visibility { is_visible: false },
         ..default()
     };
 
-    //println!("food pos: x:{x} y:{y}");
-
     commands.spawn()
         .insert(Food)
         .insert(game::GameTag)
         .insert_bundle(sprite_sheet_bundle);
-
-
 }
 
-/*
+//Eat phase: the snake already grew on the step it ate, this puts the next piece of food down
 pub fn eat_food(
-    mut commands: Commands,
-    mut set: ParamSet<(Query<(&mut Transform), With<Food>>, Query<(&Transform, &mut snake::SnakeHead)>)>,
-    mut snake_event: EventWriter<snake::AddToSnakeBody>,
+    tick: Res<game::CurrentTick>,
+    world: Option<ResMut<SnakeWorld>>,
+    mut rng: ResMut<rng::GameRng>,
+    mut board_full_event: EventWriter<BoardFull>,
 ) {
+    let mut world = match world {
+        Some(world) => world,
+        None => return,
+    };
+    if !matches!(tick.outcome, Some(world::TickOutcome::Ate(_))) { return }
 
-    if set.p0().is_empty() || set.p1().is_empty() {
-        return;
-    }
-
-    let mut same_pos = false;
-    {
-        let mut snake_query = set.p1();
-        let (snake_transform, mut snake_head) = snake_query.single_mut();
-        let snake_pos: Vec2 = Vec2::new(snake_transform.translation.x, snake_transform.translation.y);
-
-        let mut food_query = set.p0();
-        let mut food_transform = food_query.single_mut();
-        let food_pos: Vec2 = Vec2::new(food_transform.translation.x, food_transform.translation.y);
-        
-        if food_pos.eq(&snake_pos) {
-            same_pos = true;
-            food_transform.translation = new_food_pos();
-        } 
-    }
-
-    if same_pos {
-        snake_event.send(snake::AddToSnakeBody);
-
-        let mut snake_query = set.p1();
-        let (snake_transform, mut snake_head) = snake_query.single_mut();
-        let snake_pos: Vec2 = Vec2::new(snake_transform.translation.x, snake_transform.translation.y);
-
-        snake_head.score += 1;
-        snake_event.send(snake::AddToSnakeBody);
+    if world.food().is_empty() && world.spawn_food(&mut *rng).is_none() {
+        board_full_event.send(BoardFull);
     }
-
 }
-*/
 
-pub fn eat_food(
-    mut commands: Commands,
-    mut set: ParamSet<(Query<(&mut Transform), With<Food>>, Query<(&Transform, &mut snake::SnakeHead)>)>,
-    mut snake_event: EventWriter<snake::AddToSnakeBody>,
+//keeps the sprite on top of the food in the world
+pub fn update(
+    arena: Res<arena::ArenaConfig>,
+    world: Option<Res<SnakeWorld>>,
+    mut food_query: Query<(&mut Transform, &mut Visibility), With<Food>>,
+    game_state: Res<State<GameState>>,
 ) {
-    if set.p0().is_empty() || set.p1().is_empty() {
-        return;
-    }
-
-    let mut snake_query = set.p1();
-    let (snake_transform, _) = snake_query.single();
-    let snake_pos = snake_transform.translation.truncate();
-
-    let mut food_query = set.p0();
-    let mut food_transform = food_query.single_mut();
-    let food_pos = food_transform.translation.truncate();
-
-    if food_pos.eq(&snake_pos) {       
-        food_transform.translation = new_food_pos();
-        snake_event.send(snake::AddToSnakeBody);
-
-        let mut snake_query = set.p1();
-        let (_, mut snake_head) = snake_query.single_mut();
-        snake_head.score += 1;
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+    if food_query.is_empty() { return }
+    //once the run is over the board is left to the screens on top of it
+    if game_state.current() != &GameState::InGame { return }
+
+    let (mut transform, mut visibility) = food_query.single_mut();
+    match world.food().first() {
+        Some(cell) => {
+            transform.translation = arena.cell_to_translation(*cell, arena::SPRITE_Z);
+            visibility.is_visible = true;
+        }
+        None => visibility.is_visible = false,
     }
 }
-
-fn new_food_pos() -> Vec3 {
-    let width = (arena::ARENA_SIZE as i32) - 16;
-    let height = (arena::ARENA_SIZE as i32);;
-
-    let x: i32 = 16 * rand::thread_rng().gen_range(1..=(width/16));
-    let y: i32 = 16 * rand::thread_rng().gen_range(1..=(height/16));
-
-    Vec3::new(x as f32, y as f32, 0.)
-}
\ No newline at end of file
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::*;
+use crate::game::*;
+use crate::game::replay::Replay;
+use crate::game::world::{SnakeWorld, TickOutcome};
+
+/*
+    The ghost, the best recorded run of the mode and difficulty played again next to the
+    live one and drawn see-through under the real snake. It runs on a model of its own
+    with its own clock and rng, so it is never part of the `SnakeWorld`: it cannot be run
+    into and it does not change where the live food goes. It only shows up when turned
+    on, and when one of the runs on the leaderboard has a replay that was played on a
+    board like this.
+ */
+
+//whether the ghost is raced, picked on the menu
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ShowGhost(pub bool);
+
+pub struct Ghost {
+    replay: Replay,
+    world: SnakeWorld,
+    rng: GameRng,
+    clock: TickClock,
+    score: i32,
+    //one sprite per cell of the body, head first
+    sprites: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct GhostTag;
+
+/*
+    Replays read for the ghost so far, by file name. A replay file is never written to
+    again once saved, so each one is read once no matter how often a run is restarted.
+    A file that could not be read is remembered as `None`.
+ */
+#[derive(Default)]
+pub struct GhostReplays(HashMap<String, Option<Replay>>);
+
+const GHOST_ALPHA: f32 = 0.35;
+
+//above the board tiles, under the live snake
+const GHOST_Z: f32 = 0.5;
+
+//the best run of the mode and difficulty with a replay on a board of this size, the leaderboard is already best first
+fn best_replay(
+    player_data: &PlayerData,
+    mode: GameMode,
+    difficulty: Difficulty,
+    arena: &arena::ArenaConfig,
+    replay_dir: &ReplayDir,
+    replays: &mut GhostReplays,
+) -> Option<Replay> {
+    let ruleset = replay::Ruleset { mode, width: arena.width, height: arena.height };
+
+    for file in player_data.leaderboard(mode, difficulty).iter().filter_map(|entry| entry.replay.as_ref()) {
+        let replay = replays.0.entry(file.clone())
+            .or_insert_with(|| Replay::load(&replay_dir.0.join(file)).ok());
+
+        if let Some(replay) = replay.as_ref().filter(|replay| replay.ruleset == ruleset) {
+            return Some(replay.clone());
+        }
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start(
+    mut commands: Commands,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    show_ghost: Res<ShowGhost>,
+    player_data: Res<PlayerData>,
+    replay_dir: Option<Res<ReplayDir>>,
+    mut replays: ResMut<GhostReplays>,
+    playback: Option<Res<playback::Playback>>,
+    ghost_query: Query<Entity, With<GhostTag>>,
+) {
+    //the ghost of the run before goes, it may have been left standing when that run ended
+    for entity in ghost_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<Ghost>();
+
+    if !show_ghost.0 || playback.is_some() { return }
+
+    let replay = match replay_dir.and_then(|replay_dir| best_replay(&player_data, *mode, *difficulty, &arena, &replay_dir, &mut replays)) {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    let (world, rng) = replay.start();
+    let sprites = world.body().iter()
+        .enumerate()
+        .map(|(index, cell)| spawn_sprite(&mut commands, &textures, &arena, *cell, index == 0))
+        .collect();
+
+    commands.insert_resource(Ghost {
+        clock: TickClock::new(replay.pace()),
+        replay,
+        world,
+        rng,
+        score: 0,
+        sprites,
+    });
+}
+
+//the run is over, the ghost goes with it instead of standing still behind the screens
+pub fn stop(
+    mut commands: Commands,
+    ghost_query: Query<Entity, With<GhostTag>>,
+) {
+    for entity in ghost_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<Ghost>();
+}
+
+//ticks on its own clock, the way the live snake does on `run_on_tick`
+pub fn update(
+    mut commands: Commands,
+    time: Res<Time>,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    ghost: Option<ResMut<Ghost>>,
+    mut transform_query: Query<&mut Transform, With<GhostTag>>,
+) {
+    let mut ghost = match ghost {
+        Some(ghost) => ghost,
+        None => return,
+    };
+
+    let ghost = &mut *ghost;
+    let mut stepped = false;
+    while ghost.clock.advance(time.delta_seconds_f64()) {
+        if let TickOutcome::Ate(_) = ghost.replay.step(&mut ghost.world, &mut ghost.rng) {
+            ghost.score += 1;
+            ghost.clock.set_score(ghost.score);
+        }
+        stepped = true;
+
+        //the record ends here, so does the ghost
+        if ghost.world.tick() >= ghost.replay.end_tick() || !ghost.world.is_alive() || ghost.world.is_complete() {
+            ghost.clock.hold();
+            for entity in ghost.sprites.drain(..) {
+                commands.entity(entity).despawn();
+            }
+            commands.remove_resource::<Ghost>();
+            return;
+        }
+    }
+    if !stepped { return }
+
+    //a ghost that grew gets its new segment on the tail cell, like the live snake
+    let tail = *ghost.world.body().back().unwrap();
+    while ghost.sprites.len() < ghost.world.length() {
+        let entity = spawn_sprite(&mut commands, &textures, &arena, tail, false);
+        ghost.sprites.push(entity);
+    }
+
+    for (entity, cell) in ghost.sprites.iter().zip(ghost.world.body().iter()) {
+        if let Ok(mut transform) = transform_query.get_mut(*entity) {
+            transform.translation = arena.cell_to_translation(*cell, GHOST_Z);
+        }
+    }
+}
+
+fn spawn_sprite(
+    commands: &mut Commands,
+    textures: &resources::GameTextures,
+    arena: &arena::ArenaConfig,
+    cell: world::Cell,
+    head: bool,
+) -> Entity {
+    let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
+        texture_atlas: textures.snake_atlas.clone(),
+        sprite: TextureAtlasSprite {
+            index: if head { 0 } else { 1 },
+            color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+            anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
+            ..default()
+        },
+        transform: Transform {
+            translation: arena.cell_to_translation(cell, GHOST_Z),
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn()
+        .insert(GhostTag)
+        .insert(game::GameTag)
+        .insert_bundle(sprite_sheet_bundle)
+        .id()
+}
@@ -8,10 +8,22 @@ pub struct SnakeBodyText;
 //events
 pub struct HighScoreEvent(pub i32);
 
+//where the run that just ended placed on the leaderboard of its mode and difficulty, `None` when it did not make it
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LastRun {
+    pub mode: world::GameMode,
+    pub difficulty: difficulty::Difficulty,
+    pub rank: Option<usize>,
+    //what killed the snake, `None` for a run that was won
+    pub death: Option<snake::KillSnake>,
+}
+
 pub fn setup(
     mut commands: Commands,
     font_assets: Res<resources::GameFonts>,
-    player_data: Res<game::PlayerData>
+    player_data: Res<game::PlayerData>,
+    difficulty: Res<difficulty::Difficulty>,
+    config: Res<SnakeGameConfig>,
 ) {
     let title_txt_style : TextStyle = TextStyle {
         font: font_assets.bold.clone(),
@@ -19,9 +31,11 @@ pub fn setup(
         color: Color::rgb(1., 1., 1.),
     };
 
-    let mut hs_string: String = "HIGHSCORE: ".to_string();
-    let num_string: String = player_data.highscore.to_string();
-    hs_string.push_str(num_string.as_str());
+    let hs_string = if config.custom_pace(*difficulty) {
+        "UNRANKED (CUSTOM SPEED)".to_string()
+    } else {
+        highscore_string(*difficulty, player_data.highscore(*difficulty))
+    };
 
     commands.spawn_bundle(TextBundle {
         text: Text {
@@ -37,27 +51,58 @@ pub fn setup(
     .insert(SnakeBodyText);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn highscore_event(
     mut highscore_event: EventReader<HighScoreEvent>,
     mut query: Query<(&mut Text), With<SnakeBodyText>>,
-    mut player_data: ResMut<game::PlayerData>
+    mut player_data: ResMut<game::PlayerData>,
+    mut last_run: ResMut<LastRun>,
+    difficulty: Res<difficulty::Difficulty>,
+    mode: Res<world::GameMode>,
+    world: Option<Res<world::SnakeWorld>>,
+    run_timer: Res<game::RunTimer>,
+    rng: Res<rng::GameRng>,
+    recorder: Res<replay::ReplayRecorder>,
+    config: Res<SnakeGameConfig>,
 ) {
-    if query.is_empty() { return }
-
-    let mut text = query.single_mut();
-
     for event in highscore_event.iter() {
         let score: i32 = event.0;
-  
-        if player_data.highscore >= score { return }
 
-        player_data.highscore = score;
+        //the scores of a difficulty are only ever from its own speed
+        if config.custom_pace(*difficulty) {
+            last_run.mode = *mode;
+            last_run.difficulty = *difficulty;
+            last_run.rank = None;
+            continue;
+        }
 
-        let mut hs_string: String = "HIGHSCORE: ".to_string();
-        let num_string: String = player_data.highscore.to_string();
-        hs_string.push_str(num_string.as_str());
+        let entry = leaderboard::LeaderboardEntry {
+            name: player_data.player_name().to_string(),
+            score,
+            length: world.as_ref().map(|world| world.length()).unwrap_or(0),
+            duration: run_timer.0.elapsed_secs() as f64,
+            seed: rng.seed(),
+            difficulty: *difficulty,
+            timestamp: leaderboard::unix_now(),
+            replay: recorder.last_file.clone(),
+        };
+        //the death was already put down by `kill_snake`
+        last_run.mode = *mode;
+        last_run.difficulty = *difficulty;
+        last_run.rank = player_data.record_run(*mode, entry);
 
-        text.sections[0].value = hs_string;
+        if player_data.highscore(*difficulty) >= score { continue }
+
+        player_data.set_highscore(*difficulty, score);
+
+        for mut text in query.iter_mut() {
+            text.sections[0].value = highscore_string(*difficulty, score);
+        }
     }
     
+}
+
+//scores are kept per difficulty, so the label says which one is shown
+fn highscore_string(difficulty: difficulty::Difficulty, score: i32) -> String {
+    format!("HIGHSCORE ({}): {}", difficulty.name().to_uppercase(), score)
 }
\ No newline at end of file
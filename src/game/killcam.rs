@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::*;
+use crate::game::*;
+use crate::game::world::{Cell, DeathCause, SnakeWorld, TickOutcome};
+
+/*
+    The last few seconds of a run played again in slow motion behind the death screen,
+    with the cell the snake died on marked. The board is written into a rolling buffer
+    every tick, so there is nothing to work out once the run is over. The buttons of the
+    death screen only show up when it is done, Escape skips it.
+ */
+#[derive(Default)]
+pub struct Killcam {
+    frames: VecDeque<KillcamFrame>,
+    //how long the frames in the buffer were on screen together
+    seconds: f64,
+    //the frame on the board while it plays, `None` before the death and once it is done
+    shown: Option<usize>,
+    timer: f64,
+    //the pooled sprites, one per body cell head first and one per piece of food
+    body_sprites: Vec<Entity>,
+    food_sprites: Vec<Entity>,
+}
+
+//the board after one tick
+struct KillcamFrame {
+    body: Vec<Cell>,
+    food: Vec<Cell>,
+    //how long it was on screen, the tick interval at the time
+    seconds: f64,
+}
+
+impl Killcam {
+    pub fn is_playing(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    fn push(&mut self, world: &SnakeWorld, seconds: f64) {
+        self.frames.push_back(KillcamFrame {
+            body: world.body().iter().copied().collect(),
+            food: world.food().to_vec(),
+            seconds,
+        });
+        self.seconds += seconds;
+
+        while self.frames.len() > 1 && self.seconds - self.frames[0].seconds >= KILLCAM_SECONDS {
+            let frame = self.frames.pop_front().unwrap();
+            self.seconds -= frame.seconds;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct KillcamTag;
+
+//of game time kept in the buffer
+const KILLCAM_SECONDS: f64 = 3.;
+
+//how fast the killcam plays, half speed stretches the last seconds enough to follow them
+const KILLCAM_SPEED: f64 = 0.5;
+
+//above the board, the snake is gone by the time it plays
+const KILLCAM_Z: f32 = arena::SPRITE_Z;
+const FATAL_Z: f32 = arena::SPRITE_Z + 0.5;
+
+//a new run starts with an empty buffer
+pub fn reset(mut killcam: ResMut<Killcam>) {
+    *killcam = Killcam::default();
+}
+
+//runs after the tick has settled, the world has stepped and new food is down
+pub fn record(
+    tick: Res<CurrentTick>,
+    clock: Res<difficulty::TickClock>,
+    world: Option<Res<SnakeWorld>>,
+    mut killcam: ResMut<Killcam>,
+) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+
+    //the tick the snake died on does not move it, the last frame already shows where it was
+    if matches!(tick.outcome, Some(TickOutcome::Moved) | Some(TickOutcome::Ate(_))) {
+        killcam.push(&world, clock.interval());
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn start(
+    mut commands: Commands,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    clock: Res<difficulty::TickClock>,
+    world: Option<Res<SnakeWorld>>,
+    last_run: Res<highscore::LastRun>,
+    mut killcam: ResMut<Killcam>,
+    mut food_query: Query<&mut Visibility, With<food::Food>>,
+    mut transform_query: Query<(&mut Transform, &mut Visibility), (With<KillcamTag>, Without<food::Food>)>,
+) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+
+    /*
+        From the `KillSnake` that ended the run, so a death sent by a host app is marked where
+        it says. The cell the head was going into, just off the board for a wall, or the cell
+        of the event itself when the snake was killed by something else.
+     */
+    let fatal = match last_run.death {
+        Some(kill) => match kill.cause {
+            DeathCause::Wall(_) | DeathCause::OwnBody(_) => world.next_cell(kill.cell, world.direction()),
+            _ => kill.cell,
+        },
+        None => world.head(),
+    };
+
+    //died before the first move
+    if killcam.frames.is_empty() {
+        killcam.push(&world, clock.interval());
+    }
+
+    killcam.shown = Some(0);
+    killcam.timer = 0.;
+
+    //the killcam brings its own food, the real one is where the food is now
+    for mut visibility in food_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(1., 0.1, 0.1, 0.6),
+            anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
+            ..default()
+        },
+        transform: Transform {
+            translation: arena.cell_to_translation(fatal, FATAL_Z),
+            ..default()
+        },
+        ..default()
+    })
+    .insert(KillcamTag)
+    .insert(game::GameTag);
+
+    show_frame(&mut commands, &textures, &arena, &mut killcam, 0, &mut transform_query);
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update(
+    mut commands: Commands,
+    time: Res<Time>,
+    input: Res<Input<KeyCode>>,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    mut killcam: ResMut<Killcam>,
+    mut transform_query: Query<(&mut Transform, &mut Visibility), (With<KillcamTag>, Without<food::Food>)>,
+) {
+    let shown = match killcam.shown {
+        Some(shown) => shown,
+        None => return,
+    };
+    let last = killcam.frames.len() - 1;
+
+    //skipping goes straight to the end, the marked cell stays up
+    if input.just_pressed(KeyCode::Escape) {
+        show_frame(&mut commands, &textures, &arena, &mut killcam, last, &mut transform_query);
+        killcam.shown = None;
+        return;
+    }
+
+    killcam.timer += time.delta_seconds_f64() * KILLCAM_SPEED;
+    if killcam.timer < killcam.frames[shown].seconds { return }
+    killcam.timer -= killcam.frames[shown].seconds;
+
+    if shown >= last {
+        killcam.shown = None;
+        return;
+    }
+
+    killcam.shown = Some(shown + 1);
+    show_frame(&mut commands, &textures, &arena, &mut killcam, shown + 1, &mut transform_query);
+}
+
+//puts the sprites on the cells of one frame, spawning more when the snake got longer than any frame so far
+#[allow(clippy::type_complexity)]
+fn show_frame(
+    commands: &mut Commands,
+    textures: &resources::GameTextures,
+    arena: &arena::ArenaConfig,
+    killcam: &mut Killcam,
+    index: usize,
+    transform_query: &mut Query<(&mut Transform, &mut Visibility), (With<KillcamTag>, Without<food::Food>)>,
+) {
+    let frame = &killcam.frames[index];
+
+    let pools = [
+        (&frame.body, &mut killcam.body_sprites, false),
+        (&frame.food, &mut killcam.food_sprites, true),
+    ];
+
+    for (cells, sprites, food) in pools {
+        for (i, cell) in cells.iter().enumerate() {
+            let translation = arena.cell_to_translation(*cell, KILLCAM_Z);
+
+            match sprites.get(i) {
+                Some(entity) => {
+                    if let Ok((mut transform, mut visibility)) = transform_query.get_mut(*entity) {
+                        transform.translation = translation;
+                        visibility.is_visible = true;
+                    }
+                }
+                None => {
+                    let atlas_index = if food { 2 } else if i == 0 { 0 } else { 1 };
+                    let entity = spawn_sprite(commands, textures, arena, translation, atlas_index);
+                    sprites.push(entity);
+                }
+            }
+        }
+
+        for entity in sprites.iter().skip(cells.len()) {
+            if let Ok((_, mut visibility)) = transform_query.get_mut(*entity) {
+                visibility.is_visible = false;
+            }
+        }
+    }
+}
+
+fn spawn_sprite(
+    commands: &mut Commands,
+    textures: &resources::GameTextures,
+    arena: &arena::ArenaConfig,
+    translation: Vec3,
+    index: usize,
+) -> Entity {
+    let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
+        texture_atlas: textures.snake_atlas.clone(),
+        sprite: TextureAtlasSprite {
+            index,
+            anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
+            ..default()
+        },
+        transform: Transform {
+            translation,
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn()
+        .insert(KillcamTag)
+        .insert(game::GameTag)
+        .insert_bundle(sprite_sheet_bundle)
+        .id()
+}
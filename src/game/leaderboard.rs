@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::difficulty::Difficulty;
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+pub const DEFAULT_PLAYER_NAME: &str = "Player";
+pub const MAX_NAME_LENGTH: usize = 12;
+
+//one finished run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: i32,
+    pub length: usize,
+    //in seconds
+    pub duration: f64,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    //seconds since the unix epoch
+    pub timestamp: u64,
+    //the file name of the run in the replay dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<String>,
+}
+
+impl LeaderboardEntry {
+    //is this run placed above `other`, a tie goes to the faster run and then to the older one
+    fn beats(&self, other: &LeaderboardEntry) -> bool {
+        (self.score, -self.duration, std::cmp::Reverse(self.timestamp))
+            > (other.score, -other.duration, std::cmp::Reverse(other.timestamp))
+    }
+}
+
+//the best runs of one game mode, best first
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    pub fn entry_mut(&mut self, rank: usize) -> Option<&mut LeaderboardEntry> {
+        self.entries.get_mut(rank)
+    }
+
+    //puts the run in its place, returns its rank from 0 or `None` when it did not make the cut
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let rank = self.entries.iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or(self.entries.len());
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+}
+
+pub fn unix_now() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        0
+    }
+}
+
+//`2022-08-14` in UTC, the calendar math is Howard Hinnant's `civil_from_days`
+pub fn date_string(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+//`m:ss`, the way run times are shown everywhere
+pub fn duration_string(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: i32, duration: f64, timestamp: u64) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: DEFAULT_PLAYER_NAME.to_string(),
+            score,
+            length: 3 + score as usize,
+            duration,
+            seed: 0,
+            difficulty: Difficulty::Normal,
+            timestamp,
+            replay: None,
+        }
+    }
+
+    #[test]
+    fn ties_go_to_the_faster_then_the_older_run() {
+        assert!(entry(5, 90., 100).beats(&entry(4, 10., 0)));
+        assert!(entry(5, 60., 100).beats(&entry(5, 90., 0)));
+        assert!(entry(5, 60., 100).beats(&entry(5, 60., 200)));
+        assert!(!entry(5, 60., 100).beats(&entry(5, 60., 100)));
+    }
+
+    #[test]
+    fn insert_returns_the_rank() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.insert(entry(5, 60., 0)), Some(0));
+        assert_eq!(leaderboard.insert(entry(8, 60., 1)), Some(0));
+        assert_eq!(leaderboard.insert(entry(5, 50., 2)), Some(1));
+        //the same run again places under the one that got there first
+        assert_eq!(leaderboard.insert(entry(5, 60., 3)), Some(3));
+
+        let scores: Vec<_> = leaderboard.entries().iter().map(|entry| (entry.score, entry.timestamp)).collect();
+        assert_eq!(scores, [(8, 1), (5, 2), (5, 0), (5, 3)]);
+    }
+
+    #[test]
+    fn a_full_board_keeps_the_best_ten() {
+        let mut leaderboard = Leaderboard::default();
+        for score in 1..=LEADERBOARD_SIZE as i32 {
+            leaderboard.insert(entry(score, 60., 0));
+        }
+        assert_eq!(leaderboard.insert(entry(0, 60., 0)), None);
+        assert_eq!(leaderboard.insert(entry(1, 60., 1)), None);
+
+        assert_eq!(leaderboard.insert(entry(4, 60., 1)), Some(LEADERBOARD_SIZE - 3));
+        assert_eq!(leaderboard.entries().len(), LEADERBOARD_SIZE);
+        assert_eq!(leaderboard.entries().last().unwrap().score, 2);
+    }
+
+    #[test]
+    fn dates_are_in_utc() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(1_660_435_200 + 86_399), "2022-08-14");
+        assert_eq!(date_string(1_672_444_800), "2022-12-31");
+        assert_eq!(date_string(1_672_531_200), "2023-01-01");
+    }
+
+    #[test]
+    fn dates_know_the_leap_years() {
+        assert_eq!(date_string(1_709_164_800), "2024-02-29");
+        //every 400 years is a leap year, every 100 is not
+        assert_eq!(date_string(951_782_400), "2000-02-29");
+        assert_eq!(date_string(951_868_800), "2000-03-01");
+        assert_eq!(date_string(4_107_456_000), "2100-02-28");
+        assert_eq!(date_string(4_107_542_400), "2100-03-01");
+    }
+}
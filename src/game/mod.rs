@@ -1,18 +1,74 @@
 use bevy::prelude::*;
-use bevy::core::FixedTimestep;
+use bevy::core::Stopwatch;
 use iyes_loopless::prelude::AppLooplessStateExt;
 
 use crate::*;
 
+pub mod world;
+pub mod rng;
+pub mod difficulty;
 pub mod snake;
 pub mod food;
-mod highscore;
-mod arena;
+pub mod highscore;
+pub mod leaderboard;
+pub mod replay;
+pub mod playback;
+pub mod ghost;
+pub mod killcam;
+pub mod arena;
 mod death_screen;
+mod win_screen;
+mod pause_screen;
 
 #[derive(Component)]
 pub struct GameTag;
 
+/*
+    The phases of one movement tick, run in this order and only while in game.
+    Systems of other plugins can be placed around them with `.before`/`.after`,
+    and `.with_run_criteria(OnTick)` makes a system set run once per tick as well.
+    The keys are read into the turn queue every frame before Input takes a turn from it.
+    Grow is not a tick phase, it runs every frame after Eat so an `AddToSnakeBody`
+    sent between two ticks is not lost, and the growth shows up on the next tick.
+    The collision check is part of Move, `SnakeWorld::step` decides whether the snake
+    died and puts it in `CurrentTick::outcome`. Collide only sends the `KillSnake` for it,
+    so a system between the two sees the death before anything acts on it.
+ */
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TickPhase {
+    Input,
+    Move,
+    Collide,
+    Eat,
+    //every frame, not only on a tick
+    Grow,
+    Score,
+}
+
+#[derive(RunCriteriaLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnTick;
+
+//the systems that end a run, the run is written down after them so the screens that follow can show where it placed
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct EndOfRun;
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct StartOfRun;
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct ReplaySaved;
+
+//what happened in the tick being run, filled in phase by phase
+#[derive(Default)]
+pub struct CurrentTick {
+    pub turn: Option<world::Direction>,
+    pub outcome: Option<world::TickOutcome>,
+}
+
+//how long the current run has been going, only ticks while in game
+#[derive(Default)]
+pub struct RunTimer(pub Stopwatch);
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
@@ -22,42 +78,121 @@ impl Plugin for GamePlugin {
             .add_event::<snake::AddToSnakeBody>()
             .add_event::<snake::KillSnake>()
             .add_event::<highscore::HighScoreEvent>()
+            .add_event::<food::BoardFull>()
+            .init_resource::<RunTimer>()
+            .init_resource::<difficulty::TickClock>()
+            .init_resource::<CurrentTick>()
+            .init_resource::<highscore::LastRun>()
+            .init_resource::<replay::ReplayRecorder>()
+            .init_resource::<killcam::Killcam>()
+            .init_resource::<ghost::GhostReplays>()
 
             .add_system_set(SystemSet::on_enter(AppState::Game)
                 .with_system(arena::setup)
                 .with_system(highscore::setup)
                 .with_system(food::spawn)
                 .with_system(snake::setup)
+                .with_system(playback::setup)
             )
                 
             .add_system_set(SystemSet::on_update(AppState::Game)
-                .with_system(snake::input)
-                .with_system(snake::kill_update)
-                .with_system(snake::kill_snake)
-                .with_system(snake::add_to_snake)
-                .with_system(food::eat_food)
-                .with_system(highscore::highscore_event)
+                .with_system(snake::input.before(TickPhase::Input))
+                //after the tick that killed the snake, so the death is handled in the frame it happened
+                .with_system(snake::kill_snake.label(EndOfRun).after(TickPhase::Collide))
+                .with_system(snake::add_to_snake.label(TickPhase::Grow).after(TickPhase::Eat))
+                .with_system(food::update.after(TickPhase::Eat))
+                .with_system(snake::win_game.label(EndOfRun).after(TickPhase::Eat))
+                .with_system(replay::finish_recording.label(ReplaySaved).after(EndOfRun))
+                .with_system(highscore::highscore_event.after(ReplaySaved))
+                .with_system(playback::controls)
+                .with_system(playback::go_to_tick)
+                .with_system(playback::scrub)
+                .with_system(playback::button_effects)
+                .with_system(playback::stop_at_end.after(TickPhase::Score))
+                .with_system(playback::update_hud.after(TickPhase::Score))
+            )
+
+            .add_system_set(SystemSet::on_exit(AppState::Game)
+                .with_system(playback::stop)
             )
             
             .add_system_set(SystemSet::on_enter(GameState::InGame)
-                .with_system(snake::spawn)
+                .with_system(snake::spawn.label(StartOfRun))
+                .with_system(replay::start_recording.after(StartOfRun))
+                //the recorder stamps the duration of a run cut short by a restart with it first
+                .with_system(reset_run_timer.after(replay::start_recording))
+                .with_system(reset_last_run)
+                .with_system(ghost::start)
+                .with_system(killcam::reset)
+                .with_system(despawn_screen::<game::killcam::KillcamTag>)
                 .with_system(despawn_screen::<game::death_screen::DeathScreenTag>)
+                .with_system(despawn_screen::<game::win_screen::WinScreenTag>)
+            )
+
+            .add_system_set(SystemSet::on_update(GameState::InGame)
+                .with_system(tick_run_timer)
+                .with_system(ghost::update)
+                .with_system(pause_screen::pause_input.after(EndOfRun))
+            )
+
+            .add_system_set(SystemSet::on_enter(GameState::Paused)
+                .with_system(pause_screen::setup)
+            )
+
+            .add_system_set(SystemSet::on_update(GameState::Paused)
+                .with_system(pause_screen::resume_input)
+                .with_system(death_screen::button_effects)
+                .with_system(pause_screen::button_update)
+            )
+
+            .add_system_set(SystemSet::on_exit(GameState::Paused)
+                .with_system(despawn_screen::<game::pause_screen::PauseScreenTag>)
             )
 
             .add_system_set(SystemSet::on_enter(GameState::Dead)
+                .with_system(ghost::stop)
                 .with_system(death_screen::setup)
+                .with_system(killcam::start)
             )
 
             .add_system_set(SystemSet::on_update(GameState::Dead)
+                .with_system(killcam::update)
+                .with_system(death_screen::show_buttons)
                 .with_system(death_screen::button_effects)
                 .with_system(death_screen::button_update)
+                .with_system(death_screen::submit_name)
+            )
+
+            .add_system_set(SystemSet::on_enter(GameState::Won)
+                .with_system(ghost::stop)
+                .with_system(win_screen::setup)
+            )
+
+            .add_system_set(SystemSet::on_update(GameState::Won)
+                .with_system(death_screen::button_effects)
+                .with_system(death_screen::button_update)
+                .with_system(death_screen::submit_name)
+            )
+
+            .add_system_set(SystemSet::on_exit(GameState::Won)
+                .with_system(snake::despawn)
             )
 
             .add_system_set(
                 SystemSet::new()
-                    .with_run_criteria(FixedTimestep::step(0.1))
-                    .with_system(snake::update)
-            );
+                    .with_run_criteria(difficulty::run_on_tick.label(OnTick))
+                    .with_system(snake::take_turn.label(TickPhase::Input))
+                    .with_system(snake::move_snake.label(TickPhase::Move).after(TickPhase::Input))
+                    .with_system(snake::collide.label(TickPhase::Collide).after(TickPhase::Move))
+                    .with_system(food::eat_food.label(TickPhase::Eat).after(TickPhase::Collide))
+                    .with_system(snake::score.label(TickPhase::Score).after(TickPhase::Grow))
+                    .with_system(replay::record_tick.after(TickPhase::Score))
+                    .with_system(killcam::record.after(TickPhase::Score))
+            )
+
+            //before `Update`, so a jump is done before the ticks of the frame
+            .add_system_to_stage(CoreStage::PreUpdate, playback::seek)
+            .add_system_to_stage(CoreStage::Last, replay::finish_on_exit);
 
     }
 }
@@ -69,6 +204,18 @@ fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands
     }
 }
 
+fn reset_run_timer(mut run_timer: ResMut<RunTimer>) {
+    run_timer.0.reset();
+}
+
+fn reset_last_run(mut last_run: ResMut<highscore::LastRun>) {
+    *last_run = highscore::LastRun::default();
+}
+
+fn tick_run_timer(time: Res<Time>, mut run_timer: ResMut<RunTimer>) {
+    run_timer.0.tick(time.delta());
+}
+
 fn setup(
 
 ) {
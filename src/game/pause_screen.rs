@@ -0,0 +1,182 @@
+use bevy::ecs::schedule::StateError;
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+
+use crate::*;
+use crate::game::snake::{SnakeBody, SnakeHead};
+
+#[derive(Component)]
+pub enum PauseButtonAction {
+    Resume,
+    Restart,
+    Menu
+}
+
+#[derive(Component)]
+pub struct PauseScreenTag;
+
+const PAUSE_KEYS: [KeyCode; 2] = [KeyCode::Escape, KeyCode::P];
+
+/*
+    Paused is pushed on top of InGame, so everything that only runs in game stops
+    and the run carries on untouched once it is popped again. Runs after the systems
+    that end a run, a snake that died or won this frame is not paused.
+ */
+pub fn pause_input(
+    mut input: ResMut<Input<KeyCode>>,
+    mut focus_event: EventReader<WindowFocused>,
+    mut game_state: ResMut<State<GameState>>,
+    world: Option<Res<game::world::SnakeWorld>>,
+    playback: Option<Res<game::playback::Playback>>,
+) {
+    let lost_focus = focus_event.iter().any(|event| !event.focused);
+    //a replay has its own pause, see `playback::controls`
+    if playback.is_some() { return }
+    if !matches!(world, Some(world) if world.is_alive() && !world.is_complete()) { return }
+
+    if lost_focus || PAUSE_KEYS.iter().any(|key| input.just_pressed(*key)) {
+        match game_state.push(GameState::Paused) {
+            //so the same press does not resume the game again this frame
+            Ok(()) => for key in PAUSE_KEYS {
+                input.clear_just_pressed(key);
+            },
+            //the state is already changing this frame, that change goes first
+            Err(StateError::StateAlreadyQueued) => {}
+            Err(error) => warn!("could not pause: {error:?}"),
+        }
+    }
+}
+
+pub fn resume_input(
+    mut input: ResMut<Input<KeyCode>>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if PAUSE_KEYS.iter().any(|key| input.just_pressed(*key)) {
+        for key in PAUSE_KEYS {
+            input.clear_just_pressed(key);
+        }
+        game_state.pop();
+    }
+}
+
+pub fn setup(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    config: Res<SnakeGameConfig>,
+) { 
+    let button_style: Style = Style {
+        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+        margin: Rect::all(Val::Px(8.)),
+        padding: Rect::all(Val::Px(8.)),
+        align_content: AlignContent::Center,
+        align_items: AlignItems::Center,
+        align_self: AlignSelf::Center,
+        justify_content: JustifyContent::Center,
+        ..Default::default()
+    };
+
+    let button_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 20.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
+    let title_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 40.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect::all(Val::Px(0.)),
+            margin: Rect::all(Val::Px(16.)),
+            padding: Rect::all(Val::Px(16.)),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_content: AlignContent::Center,
+            align_items: AlignItems::Center,
+            align_self: AlignSelf::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::rgba(0., 0., 0., 0.5)),
+        ..Default::default()
+    })
+    .insert(PauseScreenTag)
+    .insert(game::GameTag)
+    .id();
+    
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "PAUSED".to_string(),
+                style: title_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    let buttons = [
+        ("RESUME", PauseButtonAction::Resume),
+        ("RESTART", PauseButtonAction::Restart),
+        ("MENU", PauseButtonAction::Menu),
+    ];
+
+    //MENU goes to `AppState::Menu`, an embedded game without menus never built it
+    let buttons = buttons.into_iter().filter(|(_, action)| config.menus || !matches!(action, PauseButtonAction::Menu));
+
+    for (label, action) in buttons {
+        commands.spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: label.to_string(),
+                        style: button_txt_style.clone(),
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        })
+        .insert(Parent(container))
+        .insert(action);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn button_update(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &PauseButtonAction), (Changed<Interaction>, With<Button>)>,
+    snake_query: Query<Entity, Or<(With<SnakeHead>, With<SnakeBody>)>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for (interaction, pause_button_action) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked { continue }
+
+        match pause_button_action {
+            PauseButtonAction::Resume => {
+                game_state.pop();
+            }
+            //unwinds back to a fresh InGame, which spawns a new snake
+            PauseButtonAction::Restart => {
+                for entity in snake_query.iter() {
+                    commands.entity(entity).despawn();
+                }
+                game_state.replace(GameState::InGame);
+            }
+            PauseButtonAction::Menu => {
+                game_state.replace(GameState::None);
+                app_state.set(AppState::Menu);
+            }
+        }
+    }
+}
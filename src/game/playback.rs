@@ -0,0 +1,676 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::ui::FocusPolicy;
+use bevy::window::WindowFocused;
+
+use crate::*;
+use crate::game::*;
+use crate::game::replay::{Replay, RunOutcome};
+use crate::game::snake::{SnakeBody, SnakeHead, SnakeSegments};
+use crate::game::world::SnakeWorld;
+use crate::shared::text_input::text_input_sections;
+
+/*
+    Watching a replay. It plays on the real game systems, only the turns come from the
+    replay instead of the keyboard (see `snake::take_turn`), so it plays out exactly the
+    way it did. Faster speeds put more time on the tick clock, so the tick set runs as
+    many times a frame as the speed asks for. While this resource is around nothing is
+    recorded or put on the leaderboard, and the snake stays on the board when it dies so
+    the end can be looked at and rewound. The keys and buttons at the bottom control it.
+ */
+pub struct Playback {
+    pub replay: Replay,
+    //index into `SPEEDS`
+    speed: usize,
+    paused: bool,
+    //one tick asked for while paused
+    step: bool,
+    //the tick to jump to at the start of the next frame
+    seek: Option<u64>,
+    //the player's own board, mode, difficulty and rng, put back when the playback is left
+    restore: Option<(ArenaConfig, GameMode, Difficulty, GameRng)>,
+}
+
+pub const SPEEDS: [f64; 6] = [0.25, 0.5, 1., 2., 4., 8.];
+const NORMAL_SPEED: usize = 2;
+
+//how far Page Up and Page Down jump
+const JUMP_TICKS: u64 = 50;
+
+impl Playback {
+    //swaps the board, mode, difficulty and seed of the replay in for the player's own
+    pub fn new(
+        replay: Replay,
+        arena: &mut ArenaConfig,
+        mode: &mut GameMode,
+        difficulty: &mut Difficulty,
+        rng: &mut GameRng,
+    ) -> Self {
+        let own_rng = std::mem::replace(rng, GameRng::new(Some(replay.seed)));
+        let restore = (*arena, *mode, *difficulty, own_rng);
+
+        arena.width = replay.ruleset.width;
+        arena.height = replay.ruleset.height;
+        *mode = replay.ruleset.mode;
+        *difficulty = replay.settings.difficulty;
+        //shown at the size it was played at, `fit_camera` zooms out if that does not fit the window
+        if replay.settings.tile_size > 0. && replay.settings.tile_size.is_finite() {
+            arena.tile_size = replay.settings.tile_size;
+        }
+
+        Playback {
+            replay,
+            speed: NORMAL_SPEED,
+            paused: false,
+            step: false,
+            seek: None,
+            restore: Some(restore),
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    //playing on from the very end starts over
+    pub fn toggle_pause(&mut self, tick: u64) {
+        if self.paused && tick >= self.replay.end_tick() {
+            self.seek(0);
+            self.paused = false;
+            return;
+        }
+        self.paused = !self.paused;
+    }
+
+    //one tick forward, pauses if it was playing
+    pub fn step(&mut self, tick: u64) {
+        self.paused = true;
+        self.step = tick < self.replay.end_tick();
+    }
+
+    //for the tick criteria, true once for every step asked for
+    pub fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.step)
+    }
+
+    pub fn seek(&mut self, tick: u64) {
+        self.seek = Some(tick.min(self.replay.end_tick()));
+        self.step = false;
+    }
+}
+
+#[derive(Component)]
+pub struct PlaybackTag;
+
+//tick, speed and what the playback is doing
+#[derive(Component)]
+pub struct PlaybackText;
+
+//the bar along the bottom, click or drag on it to seek
+#[derive(Component)]
+pub struct ProgressBar;
+
+#[derive(Component)]
+pub struct ProgressFill;
+
+//the row the buttons sit in, the go to field is put in there as well
+#[derive(Component)]
+pub struct ControlRow;
+
+//the go to field and its label
+#[derive(Component)]
+pub struct GoToTag;
+
+#[derive(Component)]
+pub struct GoToField;
+
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum PlaybackButton {
+    Restart,
+    StepBack,
+    Pause,
+    Step,
+    End,
+    Slower,
+    Faster,
+    GoTo,
+    Exit,
+}
+
+pub fn setup(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    playback: Option<Res<Playback>>,
+) {
+    if playback.is_none() { return }
+
+    let button_style: Style = Style {
+        size: Size::new(Val::Px(44.0), Val::Px(28.0)),
+        margin: Rect::all(Val::Px(3.)),
+        align_content: AlignContent::Center,
+        align_items: AlignItems::Center,
+        align_self: AlignSelf::Center,
+        justify_content: JustifyContent::Center,
+        ..Default::default()
+    };
+
+    let button_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
+    let status_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect { left: Val::Px(0.), right: Val::Px(0.), bottom: Val::Px(0.), ..Default::default() },
+            padding: Rect::all(Val::Px(6.)),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Stretch,
+            ..Default::default()
+        },
+        color: UiColor(Color::rgba(0., 0., 0., 0.6)),
+        ..Default::default()
+    })
+    .insert(PlaybackTag)
+    .insert(game::GameTag)
+    .id();
+
+    let bar = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Auto, Val::Px(10.)),
+            margin: Rect::all(Val::Px(3.)),
+            ..Default::default()
+        },
+        color: UiColor(Color::rgb(0.25, 0.25, 0.25)),
+        ..Default::default()
+    })
+    .insert(Interaction::default())
+    .insert(ProgressBar)
+    .insert(Parent(container))
+    .id();
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+            ..Default::default()
+        },
+        color: UiColor(Color::rgb(1., 0.8, 0.2)),
+        //so clicks on the filled part still reach the bar
+        focus_policy: FocusPolicy::Pass,
+        ..Default::default()
+    })
+    .insert(ProgressFill)
+    .insert(Parent(bar));
+
+    let row = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            flex_wrap: FlexWrap::Wrap,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(ControlRow)
+    .insert(Parent(container))
+    .id();
+
+    let buttons = [
+        ("|<", PlaybackButton::Restart),
+        ("<", PlaybackButton::StepBack),
+        (">||", PlaybackButton::Pause),
+        (">", PlaybackButton::Step),
+        (">|", PlaybackButton::End),
+        ("-", PlaybackButton::Slower),
+        ("+", PlaybackButton::Faster),
+        ("GO TO", PlaybackButton::GoTo),
+        ("EXIT", PlaybackButton::Exit),
+    ];
+
+    for (label, action) in buttons {
+        let wide = matches!(action, PlaybackButton::GoTo | PlaybackButton::Exit);
+        let mut style = button_style.clone();
+        if wide {
+            style.size.width = Val::Px(64.);
+        }
+
+        commands.spawn_bundle(ButtonBundle {
+            style,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: label.to_string(),
+                        style: button_txt_style.clone(),
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        })
+        .insert(Parent(row))
+        .insert(action);
+    }
+
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.)),
+            ..Default::default()
+        },
+        text: Text {
+            sections: vec![TextSection {
+                value: String::new(),
+                style: status_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(PlaybackText)
+    .insert(Parent(row));
+}
+
+#[allow(clippy::type_complexity)]
+pub fn button_effects(
+    mut interaction_query: Query<(&Interaction, &mut UiColor), (Changed<Interaction>, With<PlaybackButton>)>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::rgb(0.5, 0.5, 0.5).into();
+            }
+            Interaction::Hovered => {
+                *color = Color::rgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::None => {
+                *color = Color::rgb(1., 1., 1.).into();
+            }
+        }
+    }
+}
+
+/*
+    Space or P plays and pauses, the arrows step a tick back and forth, Page Up and
+    Page Down jump, Home and End go to the start and the end, + and - change the speed,
+    G asks for a tick to go to and Escape leaves. A lost window focus pauses.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn controls(
+    mut commands: Commands,
+    mut input: ResMut<Input<KeyCode>>,
+    interaction_query: Query<(&Interaction, &PlaybackButton), Changed<Interaction>>,
+    go_to_query: Query<Entity, With<GoToTag>>,
+    row_query: Query<Entity, With<ControlRow>>,
+    mut focus_event: EventReader<WindowFocused>,
+    font_assets: Res<resources::GameFonts>,
+    world: Option<Res<SnakeWorld>>,
+    playback: Option<ResMut<Playback>>,
+    config: Res<SnakeGameConfig>,
+    mut game_state: ResMut<State<GameState>>,
+    mut app_state: ResMut<State<AppState>>,
+    mut app_exit_event: EventWriter<AppExit>,
+) {
+    let (mut playback, world) = match (playback, world) {
+        (Some(playback), Some(world)) => (playback, world),
+        _ => return,
+    };
+    let tick = world.tick();
+
+    if focus_event.iter().any(|event| !event.focused) {
+        playback.pause();
+    }
+
+    let mut actions: Vec<PlaybackButton> = interaction_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Clicked)
+        .map(|(_, action)| *action)
+        .collect();
+
+    //while a tick is typed in the keys belong to the field, Escape only closes it
+    if !go_to_query.is_empty() {
+        if input.just_pressed(KeyCode::Escape) {
+            for entity in go_to_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    } else {
+        let keys = [
+            (KeyCode::Space, PlaybackButton::Pause),
+            (KeyCode::P, PlaybackButton::Pause),
+            (KeyCode::Left, PlaybackButton::StepBack),
+            (KeyCode::Comma, PlaybackButton::StepBack),
+            (KeyCode::Right, PlaybackButton::Step),
+            (KeyCode::Period, PlaybackButton::Step),
+            (KeyCode::Home, PlaybackButton::Restart),
+            (KeyCode::End, PlaybackButton::End),
+            (KeyCode::Down, PlaybackButton::Slower),
+            (KeyCode::Minus, PlaybackButton::Slower),
+            (KeyCode::NumpadSubtract, PlaybackButton::Slower),
+            (KeyCode::Up, PlaybackButton::Faster),
+            (KeyCode::Equals, PlaybackButton::Faster),
+            (KeyCode::NumpadAdd, PlaybackButton::Faster),
+            (KeyCode::G, PlaybackButton::GoTo),
+            (KeyCode::Escape, PlaybackButton::Exit),
+        ];
+        actions.extend(keys.iter().filter(|(key, _)| input.just_pressed(*key)).map(|(_, action)| *action));
+
+        if input.just_pressed(KeyCode::PageUp) {
+            playback.seek(tick.saturating_sub(JUMP_TICKS));
+        }
+        if input.just_pressed(KeyCode::PageDown) {
+            playback.seek(tick + JUMP_TICKS);
+        }
+    }
+
+    for action in actions {
+        match action {
+            PlaybackButton::Restart => playback.seek(0),
+            PlaybackButton::StepBack => {
+                playback.pause();
+                playback.seek(tick.saturating_sub(1));
+            }
+            PlaybackButton::Pause => playback.toggle_pause(tick),
+            PlaybackButton::Step => playback.step(tick),
+            PlaybackButton::End => {
+                let end = playback.replay.end_tick();
+                playback.seek(end);
+            }
+            PlaybackButton::Slower => playback.slower(),
+            PlaybackButton::Faster => playback.faster(),
+            PlaybackButton::GoTo => {
+                if go_to_query.is_empty() {
+                    if let Some(row) = row_query.iter().next() {
+                        playback.pause();
+                        spawn_go_to(&mut commands, row, &font_assets);
+                    }
+                }
+            }
+            PlaybackButton::Exit => {
+                //`stop` puts the player's own settings back once the game is left,
+                //and the list must not take the same Escape as a way back to the menu
+                input.clear_just_pressed(KeyCode::Escape);
+                game_state.set(GameState::None);
+                if config.menus {
+                    app_state.set(AppState::Replays);
+                } else {
+                    app_exit_event.send(AppExit);
+                }
+                return;
+            }
+        }
+    }
+}
+
+fn spawn_go_to(commands: &mut Commands, row: Entity, font_assets: &resources::GameFonts) {
+    let prompt_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.6, 0.6, 0.6),
+    };
+
+    let field_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            margin: Rect::all(Val::Px(6.)),
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(GoToTag)
+    .insert(Parent(row))
+    .id();
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "GO TO TICK ".to_string(),
+                style: prompt_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    //long enough for any tick a person is going to play to
+    let text_input = TextInput::new("", 7);
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: text_input_sections(&text_input, &field_txt_style, Color::rgb(1., 0.8, 0.2)),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .insert(text_input)
+    .insert(GoToField);
+}
+
+//Enter in the go to field, anything that is not a number just closes it
+pub fn go_to_tick(
+    mut commands: Commands,
+    mut submitted_event: EventReader<TextSubmitted>,
+    field_query: Query<(), With<GoToField>>,
+    go_to_query: Query<Entity, With<GoToTag>>,
+    playback: Option<ResMut<Playback>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    for event in submitted_event.iter() {
+        if field_query.get(event.entity).is_err() { continue }
+
+        if let Ok(tick) = event.value.trim().parse::<u64>() {
+            playback.seek(tick);
+        }
+
+        for entity in go_to_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+//click on the bar to go to that point of the run, hold and drag to scrub through it
+pub fn scrub(
+    bar_query: Query<(&Interaction, &Node, &GlobalTransform), With<ProgressBar>>,
+    windows: Option<Res<Windows>>,
+    world: Option<Res<SnakeWorld>>,
+    playback: Option<ResMut<Playback>>,
+) {
+    let (mut playback, world) = match (playback, world) {
+        (Some(playback), Some(world)) => (playback, world),
+        _ => return,
+    };
+    let cursor = match windows.as_ref().and_then(|windows| windows.get_primary()).and_then(|window| window.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    for (interaction, node, transform) in bar_query.iter() {
+        if *interaction != Interaction::Clicked || node.size.x <= 0. { continue }
+
+        //ui nodes are placed by their center
+        let left = transform.translation.x - node.size.x / 2.;
+        let fraction = ((cursor.x - left) / node.size.x).clamp(0., 1.) as f64;
+        let tick = (fraction * playback.replay.end_tick() as f64).round() as u64;
+
+        if tick != world.tick() {
+            playback.pause();
+            playback.seek(tick);
+        }
+    }
+}
+
+//jumps to the tick asked for before the tick of the frame, the snake on the board is moved to where the model is then
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn seek(
+    mut commands: Commands,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    playback: Option<ResMut<Playback>>,
+    world: Option<ResMut<SnakeWorld>>,
+    mut rng: ResMut<GameRng>,
+    mut clock: ResMut<difficulty::TickClock>,
+    mut head_query: Query<(Entity, &mut SnakeHead, &mut SnakeSegments)>,
+    mut transform_query: Query<&mut Transform, Or<(With<SnakeHead>, With<SnakeBody>)>>,
+) {
+    let (mut playback, mut world) = match (playback, world) {
+        (Some(playback), Some(world)) => (playback, world),
+        _ => return,
+    };
+    let tick = match playback.seek.take() {
+        Some(tick) => tick,
+        None => return,
+    };
+
+    let (seek_world, seek_rng, score) = playback.replay.play_to(tick);
+    *clock = difficulty::TickClock::new(playback.replay.pace());
+    clock.set_score(score);
+    *rng = seek_rng;
+    *world = seek_world;
+
+    let (head, mut snake_head, mut segments) = match head_query.iter_mut().next() {
+        Some(snake) => snake,
+        None => {
+            snake::spawn_snake(&mut commands, &textures, &arena, &world, score);
+            return;
+        }
+    };
+    snake_head.score = score;
+
+    //the segments there already are moved over, only the difference in length is spawned or despawned
+    let body_cells = world.length() - 1;
+    while segments.0.len() > body_cells {
+        let entity = segments.0.pop_back().unwrap();
+        commands.entity(entity).despawn();
+    }
+    while segments.0.len() < body_cells {
+        let cell = world.body()[segments.0.len() + 1];
+        segments.0.push_back(snake::spawn_segment(&mut commands, &textures, &arena, cell));
+    }
+
+    for (entity, cell) in std::iter::once(&head).chain(segments.0.iter()).zip(world.body().iter()) {
+        if let Ok(mut transform) = transform_query.get_mut(*entity) {
+            transform.translation = arena.cell_to_translation(*cell, arena::SPRITE_Z);
+        }
+    }
+}
+
+//stops on the last tick of the run
+pub fn stop_at_end(
+    world: Option<Res<SnakeWorld>>,
+    playback: Option<ResMut<Playback>>,
+) {
+    if let (Some(mut playback), Some(world)) = (playback, world) {
+        //about to jump somewhere else, the world is not where it is going to be
+        if playback.seek.is_some() { return }
+
+        if world.tick() >= playback.replay.end_tick() || !world.is_alive() || world.is_complete() {
+            playback.pause();
+        }
+    }
+}
+
+pub fn update_hud(
+    world: Option<Res<SnakeWorld>>,
+    playback: Option<Res<Playback>>,
+    mut text_query: Query<&mut Text, With<PlaybackText>>,
+    mut fill_query: Query<&mut Style, With<ProgressFill>>,
+) {
+    let (playback, world) = match (playback, world) {
+        (Some(playback), Some(world)) => (playback, world),
+        _ => return,
+    };
+
+    let end = playback.replay.end_tick();
+    let tick = world.tick();
+
+    let state = if tick >= end {
+        match playback.replay.result.map(|result| result.outcome) {
+            Some(RunOutcome::Died) => "DIED",
+            Some(RunOutcome::Won) => "WON",
+            Some(RunOutcome::Abandoned) => "GAVE UP",
+            None => "END",
+        }
+    } else if playback.is_paused() {
+        "PAUSED"
+    } else {
+        "PLAYING"
+    };
+
+    let status = format!("TICK {tick}/{end}  {}x  {state}", playback.speed());
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+
+    let percent = if end == 0 { 100. } else { tick as f32 / end as f32 * 100. };
+    for mut style in fill_query.iter_mut() {
+        if style.size.width != Val::Percent(percent) {
+            style.size.width = Val::Percent(percent);
+        }
+    }
+}
+
+//leaving the game, the player's own board, mode, difficulty and rng come back
+pub fn stop(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    mut arena: ResMut<arena::ArenaConfig>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut rng: ResMut<GameRng>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    if let Some((own_arena, own_mode, own_difficulty, own_rng)) = playback.restore.take() {
+        *arena = own_arena;
+        *mode = own_mode;
+        *difficulty = own_difficulty;
+        *rng = own_rng;
+    }
+    commands.remove_resource::<Playback>();
+}
@@ -0,0 +1,416 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+use crate::game::*;
+use crate::game::snake::SnakeHead;
+use crate::game::world::{Direction, SnakeWorld, TickOutcome};
+
+/*
+    Replay files. A run is fully decided by its seed, its ruleset and the turn taken on
+    each tick, so that is all a replay has to store to be played back on the real game
+    systems. One JSON object per file, in `replays` next to the player data:
+
+        SchemaVersion  layout of the file, upgraded on load by `migrate::replay`
+        GameVersion    the release that recorded it, for information only
+        Seed           seeds the game rng, which places all the food
+        Ruleset        Mode, Width and Height of the board, these change how a run plays out
+        Settings       Difficulty, TickInterval (seconds at the start) and TileSize, for
+                       showing it the way it was played
+        Turns          [tick, direction] for every tick a turn was taken on, the turn is
+                       applied when the world steps to that tick, the first step is tick 1
+        Result         Outcome (Died, Won or Abandoned), Score, Length, Ticks, Duration in
+                       seconds and Timestamp in seconds since the unix epoch
+
+    Only the turns are recorded, so a run where another plugin sent `AddToSnakeBody` or
+    `KillSnake` will not play back the same.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Replay {
+    pub schema_version: u32,
+    pub game_version: String,
+    pub seed: u64,
+    pub ruleset: Ruleset,
+    pub settings: ReplaySettings,
+    pub turns: Vec<ReplayTurn>,
+    //missing while the run is still being recorded
+    pub result: Option<ReplayResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Ruleset {
+    pub mode: GameMode,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReplaySettings {
+    pub difficulty: Difficulty,
+    pub tick_interval: f64,
+    pub tile_size: f32,
+}
+
+//the turn taken on a tick, stored as `[tick, "Up"]` to keep the files small
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ReplayTurn(pub u64, pub Direction);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Died,
+    Won,
+    //the player restarted, went back to the menu or quit halfway
+    Abandoned,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReplayResult {
+    pub outcome: RunOutcome,
+    pub score: i32,
+    pub length: usize,
+    pub ticks: u64,
+    pub duration: f64,
+    pub timestamp: u64,
+}
+
+pub const REPLAY_DIR_NAME: &str = "replays";
+
+impl Replay {
+    pub fn new(seed: u64, ruleset: Ruleset, settings: ReplaySettings) -> Self {
+        Replay {
+            schema_version: migrate::replay::CHAIN.len() as u32,
+            game_version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            ruleset,
+            settings,
+            turns: Vec::new(),
+            result: None,
+        }
+    }
+
+    //the turn to take on `tick`, if one was taken
+    pub fn turn_at(&self, tick: u64) -> Option<Direction> {
+        self.turns.binary_search_by_key(&tick, |turn| turn.0).ok()
+            .map(|index| self.turns[index].1)
+    }
+
+    //the last tick of the run, a run that was abandoned stops where it was left
+    pub fn end_tick(&self) -> u64 {
+        match self.result {
+            Some(result) => result.ticks,
+            None => self.turns.last().map(|turn| turn.0).unwrap_or(0),
+        }
+    }
+
+    //the pace the run was played at
+    pub fn pace(&self) -> difficulty::Pace {
+        let mut pace = self.settings.difficulty.pace();
+        pace.interval = self.settings.tick_interval;
+        pace
+    }
+
+    //the world and rng the run started from, before the first tick
+    pub fn start(&self) -> (SnakeWorld, rng::GameRng) {
+        let mut rng = rng::GameRng::new(Some(self.seed));
+        rng.start_run();
+
+        let mut world = SnakeWorld::new(self.ruleset.width, self.ruleset.height, self.ruleset.mode);
+        world.spawn_food(&mut rng);
+
+        (world, rng)
+    }
+
+    /*
+        One tick of the run on the model alone. Goes through it the same way the game
+        systems do, the snake grows on the step it eats and new food is put down after
+        that, so the real systems can carry on from any point of it.
+     */
+    pub fn step(&self, world: &mut SnakeWorld, rng: &mut rng::GameRng) -> TickOutcome {
+        let outcome = world.step(self.turn_at(world.tick() + 1));
+        if world.is_alive() && world.food().is_empty() {
+            world.spawn_food(rng);
+        }
+        outcome
+    }
+
+    //the world, the rng that places the food from there on and the score, as they stood after `tick`
+    pub fn play_to(&self, tick: u64) -> (SnakeWorld, rng::GameRng, i32) {
+        let (mut world, mut rng) = self.start();
+        let mut score = 0;
+
+        while world.tick() < tick.min(self.end_tick()) && world.is_alive() && !world.is_complete() {
+            if let TickOutcome::Ate(_) = self.step(&mut world, &mut rng) {
+                score += 1;
+            }
+        }
+
+        (world, rng, score)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let file = File::open(path)?;
+        let mut value: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+        let version = migrate::migrate(&mut value, migrate::replay::CHAIN)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        //a newer layout could play back wrong without any error, so it is refused
+        if version > migrate::replay::CHAIN.len() as u32 {
+            let message = format!("recorded by a newer version of the game (replay schema {version})");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        let replay = serde_json::from_value(value)?;
+        Ok(replay)
+    }
+
+    /*
+        Written in one line, a replay is read by the game and rarely by people. Runs with
+        a fixed seed can end within the same second, so a file that is already there is
+        never overwritten, the name gets a counter instead. Returns the file name.
+     */
+    pub fn save_in(&self, dir: &Path) -> io::Result<String> {
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_string(self)?;
+
+        for attempt in 0..MAX_SAVE_ATTEMPTS {
+            let file_name = self.file_name(attempt);
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(dir.join(&file_name)) {
+                Ok(file) => file,
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            };
+            file.write_all(json.as_bytes())?;
+            return Ok(file_name);
+        }
+
+        let message = format!("{MAX_SAVE_ATTEMPTS} replays of seed {} already saved in the same second", self.seed);
+        Err(io::Error::new(io::ErrorKind::AlreadyExists, message))
+    }
+
+    //`<timestamp>-<seed>.json`, so the files sort by when they were played, then `<timestamp>-<seed>-<n>.json`
+    fn file_name(&self, attempt: u32) -> String {
+        let timestamp = self.result.map(|result| result.timestamp).unwrap_or(0);
+        match attempt {
+            0 => format!("{}-{}.json", timestamp, self.seed),
+            n => format!("{}-{}-{}.json", timestamp, self.seed, n),
+        }
+    }
+}
+
+//how many names `save_in` tries before it gives up, far more runs than fit in one second
+const MAX_SAVE_ATTEMPTS: u32 = 1000;
+
+//where replays are written, without this resource nothing is recorded to disk
+pub struct ReplayDir(pub PathBuf);
+
+//the run being recorded, and the file the last finished one was written to
+#[derive(Default)]
+pub struct ReplayRecorder {
+    replay: Option<Replay>,
+    score: i32,
+    pub last_file: Option<String>,
+}
+
+impl ReplayRecorder {
+    pub fn recording(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    replay_dir: Option<Res<ReplayDir>>,
+    world: Option<Res<SnakeWorld>>,
+    run_timer: Res<RunTimer>,
+    rng: Res<rng::GameRng>,
+    arena: Res<arena::ArenaConfig>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    config: Res<SnakeGameConfig>,
+    playback: Option<Res<playback::Playback>>,
+) {
+    //a run cut short by a restart is still worth keeping, the old world is still around at this point
+    if let Some(world) = world {
+        let score = recorder.score;
+        finish(&mut recorder, replay_dir.as_deref(), RunOutcome::Abandoned, score, &world, &run_timer);
+    }
+
+    //watching a replay is not a run of its own
+    if playback.is_some() { return }
+
+    let ruleset = Ruleset { mode: *mode, width: arena.width, height: arena.height };
+    let settings = ReplaySettings {
+        difficulty: *difficulty,
+        tick_interval: config.tick_interval.unwrap_or(difficulty.pace().interval),
+        tile_size: arena.tile_size,
+    };
+
+    recorder.replay = Some(Replay::new(rng.seed(), ruleset, settings));
+    recorder.score = 0;
+}
+
+//runs after the Score phase, the world has stepped to the tick the turn was taken on
+pub fn record_tick(
+    mut recorder: ResMut<ReplayRecorder>,
+    tick: Res<CurrentTick>,
+    world: Option<Res<SnakeWorld>>,
+    head_query: Query<&SnakeHead>,
+) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+
+    if let Some(score) = head_query.iter().map(|snake_head| snake_head.score).next() {
+        recorder.score = score;
+    }
+
+    //a world that is already over does not step, so the turn never happened
+    if tick.outcome.is_none() { return }
+
+    if let (Some(replay), Some(turn)) = (recorder.replay.as_mut(), tick.turn) {
+        replay.turns.push(ReplayTurn(world.tick(), turn));
+    }
+}
+
+//the run is over, runs before the leaderboard so the entry can point at the file
+pub fn finish_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut highscore_event: EventReader<highscore::HighScoreEvent>,
+    replay_dir: Option<Res<ReplayDir>>,
+    world: Option<Res<SnakeWorld>>,
+    run_timer: Res<RunTimer>,
+) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+
+    for event in highscore_event.iter() {
+        let outcome = if world.is_alive() { RunOutcome::Won } else { RunOutcome::Died };
+        finish(&mut recorder, replay_dir.as_deref(), outcome, event.0, &world, &run_timer);
+    }
+}
+
+//quitting in the middle of a run
+pub fn finish_on_exit(
+    app_exit_event: EventReader<AppExit>,
+    mut recorder: ResMut<ReplayRecorder>,
+    replay_dir: Option<Res<ReplayDir>>,
+    world: Option<Res<SnakeWorld>>,
+    run_timer: Res<RunTimer>,
+) {
+    if app_exit_event.is_empty() { return }
+
+    if let Some(world) = world {
+        let score = recorder.score;
+        finish(&mut recorder, replay_dir.as_deref(), RunOutcome::Abandoned, score, &world, &run_timer);
+    }
+}
+
+fn finish(
+    recorder: &mut ReplayRecorder,
+    replay_dir: Option<&ReplayDir>,
+    outcome: RunOutcome,
+    score: i32,
+    world: &SnakeWorld,
+    run_timer: &RunTimer,
+) {
+    recorder.last_file = None;
+
+    let mut replay = match recorder.replay.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+    //given up before the first move, there is nothing to watch
+    if outcome == RunOutcome::Abandoned && world.tick() == 0 { return }
+
+    replay.result = Some(ReplayResult {
+        outcome,
+        score,
+        length: world.length(),
+        ticks: world.tick(),
+        duration: run_timer.0.elapsed_secs() as f64,
+        timestamp: leaderboard::unix_now(),
+    });
+
+    let replay_dir = match replay_dir {
+        Some(replay_dir) => replay_dir,
+        None => return,
+    };
+
+    match replay.save_in(&replay_dir.0) {
+        Ok(file_name) => recorder.last_file = Some(file_name),
+        Err(error) => warn!("{}: could not save the replay: {error}", replay_dir.0.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn replay() -> Replay {
+        let ruleset = Ruleset { mode: GameMode::Wrap, width: 12, height: 9 };
+        let settings = ReplaySettings { difficulty: Difficulty::Hard, tick_interval: 0.075, tile_size: 20. };
+
+        let mut replay = Replay::new(42, ruleset, settings);
+        replay.turns = vec![ReplayTurn(3, Direction::Up), ReplayTurn(7, Direction::Right)];
+        replay.result = Some(ReplayResult {
+            outcome: RunOutcome::Died,
+            score: 2,
+            length: 4,
+            ticks: 30,
+            duration: 2.5,
+            timestamp: 1_700_000_000,
+        });
+        replay
+    }
+
+    #[test]
+    fn saved_replay_loads_the_same() {
+        let dir = TempDir::new("replay-round-trip");
+        let replay = replay();
+
+        let first = replay.save_in(&dir).unwrap();
+        let second = replay.save_in(&dir).unwrap();
+        assert_eq!(first, "1700000000-42.json");
+        assert_eq!(second, "1700000000-42-1.json");
+
+        let loaded = Replay::load(&dir.join(first)).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&replay).unwrap());
+    }
+
+    #[test]
+    fn unversioned_replay_is_the_first_layout() {
+        let dir = TempDir::new("replay-v0");
+        let path = dir.join("old.json");
+        let mut value = serde_json::to_value(replay()).unwrap();
+        value.as_object_mut().unwrap().remove(migrate::VERSION_KEY);
+        fs::write(&path, value.to_string()).unwrap();
+
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded.schema_version, migrate::replay::CHAIN.len() as u32);
+        assert_eq!(loaded.turn_at(7), Some(Direction::Right));
+    }
+
+    #[test]
+    fn newer_replay_is_refused() {
+        let dir = TempDir::new("replay-newer");
+        let path = dir.join("new.json");
+        let mut replay = replay();
+        replay.schema_version = 99;
+        fs::write(&path, serde_json::to_string(&replay).unwrap()).unwrap();
+
+        assert_eq!(Replay::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,69 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/*
+    The one source of randomness for the game module. Every run is started from a
+    single u64 seed, so knowing the seed is enough to play the exact same run again.
+    ChaCha8 is used over `StdRng` because its output is stable between rand releases.
+ */
+pub struct GameRng {
+    fixed_seed: Option<u64>,
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl GameRng {
+    //with a fixed seed every run is the same, otherwise each run gets a seed from the clock
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let seed = fixed_seed.unwrap_or_else(clock_seed);
+
+        GameRng {
+            fixed_seed,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn start_run(&mut self) {
+        self.seed = self.fixed_seed.unwrap_or_else(clock_seed);
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+//the std clock is not available in the browser
+#[cfg(target_arch = "wasm32")]
+fn clock_seed() -> u64 {
+    rand::random()
+}
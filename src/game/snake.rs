@@ -1,30 +1,46 @@
-use bevy::{prelude::*, sprite::Anchor};
+use std::collections::VecDeque;
 
-use iyes_loopless::prelude::*;
+use bevy::{prelude::*, sprite::Anchor};
 
 use crate::*;
 use crate::game::*;
 
-#[derive(Copy, Clone)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right 
-}
+use crate::game::world::{Direction, SnakeWorld, TickOutcome, TurnQueue};
 
 #[derive(Component)]
 pub struct SnakeHead {
-    direction: Direction,
+    turns: TurnQueue,
     pub score: i32
 }
 
+impl SnakeHead {
+    //queues a turn the same way a key press does, returns false when it was dropped
+    pub fn queue_turn(&mut self, turn: Direction, world: &SnakeWorld) -> bool {
+        self.turns.push(turn, world.direction())
+    }
+}
+
 #[derive(Component)]
 pub struct SnakeBody;
 
-//Events
+//the body entities owned by the head, in order from the neck to the tail
+#[derive(Component, Default)]
+pub struct SnakeSegments(pub VecDeque<Entity>);
+
+/*
+    Events. `KillSnake` is sent by the game itself when the snake dies, eating grows the
+    snake without an event. Other plugins can send either to grow or kill the snake.
+    A kill only counts in game, one sent while the game is paused is dropped.
+ */
 pub struct AddToSnakeBody;
-pub struct KillSnake;
+
+//the cell is where the head was when it died, the tick the one it died on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KillSnake {
+    pub cause: world::DeathCause,
+    pub cell: world::Cell,
+    pub tick: u64,
+}
 
 pub fn setup(
     mut game_state: ResMut<State<GameState>>,
@@ -32,179 +48,325 @@ pub fn setup(
     game_state.set(GameState::InGame);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn(
     mut commands: Commands,
     textures: Res<resources::GameTextures>,
-    mut snake_body_event: EventWriter<AddToSnakeBody>,
+    arena: Res<arena::ArenaConfig>,
+    mode: Res<world::GameMode>,
+    difficulty: Res<difficulty::Difficulty>,
+    config: Res<SnakeGameConfig>,
+    playback: Option<Res<playback::Playback>>,
+    mut rng: ResMut<rng::GameRng>,
 ) {
+    rng.start_run();
+    info!("starting run with seed {}", rng.seed());
+
+    let mut world = SnakeWorld::new(arena.width, arena.height, *mode);
+    world.spawn_food(&mut *rng);
+
+    spawn_snake(&mut commands, &textures, &arena, &world, 0);
+
+    let mut pace = difficulty.pace();
+    if let Some(interval) = config.tick_interval {
+        pace.interval = interval;
+    }
+    //a replay goes at the pace it was played at
+    if let Some(playback) = playback {
+        pace = playback.replay.pace();
+    }
+    commands.insert_resource(difficulty::TickClock::new(pace));
+
+    commands.insert_resource(world);
+}
+
+//the head and body entities for the snake in `world`, also used to rebuild it when a replay is rewound
+pub fn spawn_snake(
+    commands: &mut Commands,
+    textures: &resources::GameTextures,
+    arena: &arena::ArenaConfig,
+    world: &SnakeWorld,
+    score: i32,
+) -> Entity {
     let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
         texture_atlas: textures.snake_atlas.clone(),
         sprite: TextureAtlasSprite {
             index: 0,
             anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
             ..default()
         },
         transform: Transform {
-            translation: Vec3::new(240., 240., 0.),
+            translation: arena.cell_to_translation(world.head(), arena::SPRITE_Z),
             ..default()
         },
         ..default()
     };
 
+    let segments: VecDeque<Entity> = world.body().iter()
+        .skip(1)
+        .map(|cell| spawn_segment(commands, textures, arena, *cell))
+        .collect();
+
     commands.spawn()
         .insert(SnakeHead {
-            direction: Direction::Left,
-            score: 0
+            turns: TurnQueue::default(),
+            score
         } )
+        .insert(SnakeSegments(segments))
         .insert(game::GameTag)
-        .insert_bundle(sprite_sheet_bundle);
+        .insert_bundle(sprite_sheet_bundle)
+        .id()
+}
 
-    snake_body_event.send(snake::AddToSnakeBody);
+//Input phase: takes one queued turn for this tick, or the one the replay took on it
+pub fn take_turn(
+    mut tick: ResMut<game::CurrentTick>,
+    playback: Option<Res<playback::Playback>>,
+    world: Option<Res<SnakeWorld>>,
+    mut head_query: Query<&mut SnakeHead>,
+) {
+    tick.turn = match (playback, world) {
+        (Some(playback), Some(world)) => playback.replay.turn_at(world.tick() + 1),
+        _ => head_query.iter_mut().next().and_then(|mut snake_head| snake_head.turns.pop()),
+    };
+    tick.outcome = None;
 }
 
-pub fn update(
+//Move phase: steps the world, which also finds out if the snake died, and mirrors the model onto the sprites
+#[allow(clippy::type_complexity)]
+pub fn move_snake(
     mut commands: Commands,
-    mut app_state: Res<State<AppState>>,
-    mut set: ParamSet<(Query<(&mut Transform, &mut SnakeHead)>, Query<(&mut Transform, With<SnakeBody>)>)>,
+    textures: Res<resources::GameTextures>,
+    arena: Res<arena::ArenaConfig>,
+    world: Option<ResMut<SnakeWorld>>,
+    mut tick: ResMut<game::CurrentTick>,
+    mut head_query: Query<(&mut Transform, &mut SnakeSegments), (With<SnakeHead>, Without<SnakeBody>)>,
+    mut body_query: Query<&mut Transform, With<SnakeBody>>,
 ) {
-    //check if state is in game a hacky way to do this because bevy is dumb dumb sometimes
-    let state: &AppState = app_state.current();
-    if state.eq(&AppState::Menu) { return }
+    let mut world = match world {
+        Some(world) => world,
+        None => return,
+    };
+    if !world.is_alive() || world.is_complete() || head_query.is_empty() { return }
+
+    tick.outcome = Some(world.step(tick.turn));
+
+    let (mut head_transform, mut segments) = head_query.single_mut();
+    head_transform.translation = arena.cell_to_translation(world.head(), arena::SPRITE_Z);
 
-    //lets do our head movement update
-    let mut head_query = set.p0();
-    if head_query.is_empty() { return }
-    let (mut transform, mut snake_head) = head_query.single_mut();
-    let direction: Vec2 = Vec2::new(16.,16.) * get_velo(snake_head.direction);
-    
-    let last_pos: Vec3 = transform.translation.clone();
-    transform.translation = transform.translation + Vec3::new(direction.x, direction.y, 0.);
+    //a snake that grew this step gets its new segment on the tail cell, it unfolds from there
+    let tail = *world.body().back().unwrap();
+    while segments.0.len() + 1 < world.length() {
+        let entity = spawn_segment(&mut commands, &textures, &arena, tail);
+        segments.0.push_back(entity);
+    }
+
+    for (entity, cell) in segments.0.iter().zip(world.body().iter().skip(1)) {
+        if let Ok(mut transform) = body_query.get_mut(*entity) {
+            transform.translation = arena.cell_to_translation(*cell, arena::SPRITE_Z);
+        }
+    }
+}
+
+//Collide phase: the world already checked the move, this reports a death from it
+pub fn collide(
+    tick: Res<game::CurrentTick>,
+    world: Option<Res<SnakeWorld>>,
+    mut kill_snake: EventWriter<KillSnake>,
+) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+    if tick.outcome != Some(TickOutcome::Died) { return }
 
-    //now lets update our body
-    let mut body_query = set.p1();
-    let mut pos = last_pos;
-    for (mut transform, bool) in body_query.iter_mut() {
-        let old_trans = transform.translation.clone();
+    if let Some(cause) = world.death_cause() {
+        kill_snake.send(KillSnake {
+            cause,
+            cell: world.head(),
+            tick: world.tick(),
+        });
+    }
+}
 
-        transform.translation = pos;
+//Score phase
+pub fn score(
+    tick: Res<game::CurrentTick>,
+    mut clock: ResMut<difficulty::TickClock>,
+    mut head_query: Query<&mut SnakeHead>,
+) {
+    if !matches!(tick.outcome, Some(TickOutcome::Ate(_))) { return }
 
-        pos = old_trans;
+    for mut snake_head in head_query.iter_mut() {
+        snake_head.score += 1;
+        clock.set_score(snake_head.score);
     }
 }
 
+//queues every turn pressed this frame, `take_turn` takes one of them per tick
 pub fn input(
-    input: Res<Input<KeyCode>>, 
-    mut snake_query: Query<(&mut SnakeHead)>,
+    input: Res<Input<KeyCode>>,
+    game_state: Res<State<GameState>>,
+    playback: Option<Res<playback::Playback>>,
+    world: Option<Res<SnakeWorld>>,
+    mut snake_query: Query<&mut SnakeHead>,
 ) {
+    if game_state.current() != &GameState::InGame { return }
+    //the replay does the steering, the keys control the playback
+    if playback.is_some() { return }
+
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
     if snake_query.is_empty() {
         return;
     }
 
     let mut snake = snake_query.single_mut();
 
-    if input.pressed(KeyCode::Up) || input.pressed(KeyCode::W){
-        snake.direction = Direction::Up;
-    } else if input.pressed(KeyCode::Down) || input.pressed(KeyCode::S) {
-        snake.direction = Direction::Down;
-    } else if input.pressed(KeyCode::Left) || input.pressed(KeyCode::A) {
-        snake.direction = Direction::Left;
-    } else if input.pressed(KeyCode::Right) || input.pressed(KeyCode::D) {
-        snake.direction = Direction::Right;
+    for key in input.get_just_pressed() {
+        let turn = match key {
+            KeyCode::Up | KeyCode::W => Direction::Up,
+            KeyCode::Down | KeyCode::S => Direction::Down,
+            KeyCode::Left | KeyCode::A => Direction::Left,
+            KeyCode::Right | KeyCode::D => Direction::Right,
+            _ => continue,
+        };
+
+        snake.turns.push(turn, world.direction());
     }
 }
 
-pub fn kill_update(
+#[allow(clippy::too_many_arguments)]
+pub fn kill_snake(
     mut commands: Commands,
-    mut set: ParamSet<(Query<(Entity, &Transform, With<SnakeHead>)>, Query<(Entity, &Transform, With<SnakeBody>)>)>,
-    mut kill_snake: EventWriter<snake::KillSnake>,
+    head_query: Query<(Entity, &SnakeHead)>,
+    body_query: Query<Entity, With<SnakeBody>>,
+    world: Option<ResMut<SnakeWorld>>,
+    mut event: EventReader<KillSnake>,
+    mut highscore_event: EventWriter<highscore::HighScoreEvent>,
+    mut player_data: ResMut<PlayerData>,
+    mut last_run: ResMut<highscore::LastRun>,
+    mut game_state: ResMut<State<GameState>>,
+    playback: Option<Res<playback::Playback>>,
 ) {
-    if set.p0().is_empty() || set.p1().is_empty() {
-        return;
-    }
-    
-    let mut head_query = set.p0();
-    let (head_entity, head_transform, bool) = head_query.single();
-    let head_pos: Vec3 = head_transform.translation;
+    if body_query.is_empty() { return }
 
-    let mut body_query = set.p1();
+    let mut world = match world {
+        Some(world) => world,
+        None => return,
+    };
 
+    //the snake only dies once, the first event sent in the frame is what killed it
+    let kill = match event.iter().next() {
+        Some(kill) => *kill,
+        None => return,
+    };
+    //a kill sent while paused is dropped, setting Dead would only swap out Paused and leave InGame under it
+    if game_state.current() != &GameState::InGame { return }
 
-    //out of bounds check
-    if head_pos.y > 480. || head_pos.y <= 0. {
-        kill_snake.send(KillSnake);
+    //a replay stays on the board where it died, so the end can be looked at and rewound
+    if playback.is_some() {
+        world.kill();
         return;
     }
-
-    if head_pos.x >= 480. || head_pos.x <= 0. {
-        kill_snake.send(KillSnake);
+    //only when the run has not ended some other way this frame
+    if let Err(error) = game_state.set(GameState::Dead) {
+        warn!("the snake was not killed: {error:?}");
         return;
     }
+    world.kill();
+
+    last_run.death = Some(kill);
+    player_data.record_death(kill.cause);
 
-    //colliding with body check
-    for (body_entity, body_transform, bool) in body_query.iter() {
-        if body_transform.translation.eq(&head_pos) {
-            kill_snake.send(KillSnake);
-            return;
-        } 
+    for (head_entity, snake_head) in head_query.iter() {
+        highscore_event.send(highscore::HighScoreEvent(snake_head.score));
+        commands.entity(head_entity).despawn();
     }
 
+    for body_entity in body_query.iter() {
+        commands.entity(body_entity).despawn();
+    }
 }
 
-pub fn kill_snake(
-    mut commands: Commands,
-    head_query: Query<(Entity, &SnakeHead)>,
-    body_query: Query<(Entity, With<SnakeBody>)>,
-    mut event: EventReader<KillSnake>,
+//a full board means the snake has nowhere left to go, which is a win
+pub fn win_game(
+    mut board_full_event: EventReader<food::BoardFull>,
+    head_query: Query<&SnakeHead>,
     mut highscore_event: EventWriter<highscore::HighScoreEvent>,
     mut game_state: ResMut<State<GameState>>,
+    playback: Option<Res<playback::Playback>>,
 ) {
-    if body_query.is_empty() { return }
+    if board_full_event.iter().count() == 0 { return }
+    if game_state.current() != &GameState::InGame { return }
+    if playback.is_some() { return }
 
-    for ev in event.iter() {
-        game_state.set(GameState::Dead);
+    if let Err(error) = game_state.set(GameState::Won) {
+        warn!("the run was not won: {error:?}");
+        return;
+    }
 
-        for (head_entity, snake_head) in head_query.iter() {
-            highscore_event.send(highscore::HighScoreEvent(snake_head.score));
-            commands.entity(head_entity).despawn();
-        }
+    for snake_head in head_query.iter() {
+        highscore_event.send(highscore::HighScoreEvent(snake_head.score));
+    }
+}
 
-        for (body_entity, bool) in body_query.iter() {
-            commands.entity(body_entity).despawn();
-        }
+//clears away a snake that is still on the board once the next run begins
+pub fn despawn(
+    mut commands: Commands,
+    head_query: Query<Entity, With<SnakeHead>>,
+    body_query: Query<Entity, With<SnakeBody>>,
+) {
+    for entity in head_query.iter().chain(body_query.iter()) {
+        commands.entity(entity).despawn();
     }
 }
 
+//Grow phase: every event from another plugin adds one segment on the steps to come
 pub fn add_to_snake(
-    mut commands: Commands,
     mut event: EventReader<AddToSnakeBody>,
-    textures: Res<resources::GameTextures>, 
+    game_state: Res<State<GameState>>,
+    world: Option<ResMut<SnakeWorld>>,
 ) {
-    for ev in event.iter() {
-        let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
-            texture_atlas: textures.snake_atlas.clone(),
-            sprite: TextureAtlasSprite {
-                index: 1,
-                anchor: Anchor::TopLeft,
-                ..default()
-            },
-            transform: Transform {
-                translation: Vec3::new(2500.,2500., 0.),
-                ..default()
-            },
-            ..default()
-        };
-        
-        commands.spawn()
-            .insert(SnakeBody)
-            .insert_bundle(sprite_sheet_bundle);
+    let mut world = match world {
+        Some(world) => world,
+        None => return,
+    };
+
+    for _ in event.iter() {
+        if game_state.current() == &GameState::InGame && world.is_alive() {
+            world.grow();
+        }
     }
 }
 
-fn get_velo(dir: Direction) -> Vec2 {
-    match dir {
-        Direction::Up => return Vec2::new(0., 1.),
-        Direction::Down => return Vec2::new(0., -1.),
-        Direction::Left => return Vec2::new(-1., 0.),
-        Direction::Right => return Vec2::new(1., 0.),
-    }
-}
\ No newline at end of file
+pub fn spawn_segment(
+    commands: &mut Commands,
+    textures: &resources::GameTextures,
+    arena: &arena::ArenaConfig,
+    cell: world::Cell,
+) -> Entity {
+    let sprite_sheet_bundle: SpriteSheetBundle = SpriteSheetBundle {
+        texture_atlas: textures.snake_atlas.clone(),
+        sprite: TextureAtlasSprite {
+            index: 1,
+            anchor: Anchor::TopLeft,
+            custom_size: Some(arena.tile()),
+            ..default()
+        },
+        transform: Transform {
+            translation: arena.cell_to_translation(cell, arena::SPRITE_Z),
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn()
+        .insert(SnakeBody)
+        .insert(game::GameTag)
+        .insert_bundle(sprite_sheet_bundle)
+        .id()
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::*;
+use crate::game::death_screen;
+use crate::game::world::SnakeWorld;
+
+#[derive(Component)]
+pub struct WinScreenTag;
+
+pub fn setup(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    world: Res<SnakeWorld>,
+    run_timer: Res<game::RunTimer>,
+    config: Res<SnakeGameConfig>,
+    last_run: Res<game::highscore::LastRun>,
+    player_data: Res<PlayerData>,
+) { 
+    let title_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 40.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let stat_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 20.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let length_string = format!("LENGTH: {}", world.length());
+    let time_string = format!("TIME: {}", game::leaderboard::duration_string(run_timer.0.elapsed_secs() as f64));
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect::all(Val::Px(0.)),
+            margin: Rect::all(Val::Px(16.)),
+            padding: Rect::all(Val::Px(16.)),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_content: AlignContent::Center,
+            align_items: AlignItems::Center,
+            align_self: AlignSelf::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(WinScreenTag)
+    .insert(game::GameTag)
+    .id();
+    
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "YOU WIN".to_string(),
+                style: title_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    death_screen::spawn_rank(&mut commands, container, &font_assets, &last_run, &player_data);
+
+    for stat in [length_string, time_string] {
+        commands.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: stat,
+                    style: stat_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container));
+    }
+
+    death_screen::spawn_buttons(&mut commands, container, &font_assets, &config);
+}
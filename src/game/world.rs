@@ -0,0 +1,610 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/*
+    The pure game model. Everything in here works on integer grid cells and knows
+    nothing about Bevy, so the rules can be driven and checked without a window.
+    The systems in `snake` and `food` only copy this state onto sprites.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Cell {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Cell { x, y }
+    }
+
+    pub fn step(self, direction: Direction) -> Cell {
+        let (dx, dy) = direction.offset();
+        Cell::new(self.x + dx, self.y + dy)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+//what happens at the edge of the board
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GameMode {
+    //leaving the board is fatal
+    #[default]
+    Classic,
+    //the board is a torus, leaving one edge comes back in on the opposite one
+    Wrap,
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Wrap => "Wrap",
+        }
+    }
+
+    pub fn next(self) -> GameMode {
+        match self {
+            GameMode::Classic => GameMode::Wrap,
+            GameMode::Wrap => GameMode::Classic,
+        }
+    }
+}
+
+pub const MAX_QUEUED_TURNS: usize = 3;
+
+/*
+    Turns pressed between two ticks, applied one per tick. A turn is checked against
+    the turn queued before it, so a quick up-left while heading right is kept as two
+    turns instead of being read as a reversal into the neck.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct TurnQueue {
+    turns: VecDeque<Direction>,
+}
+
+impl TurnQueue {
+    //returns false when the turn was dropped
+    pub fn push(&mut self, turn: Direction, current: Direction) -> bool {
+        let last = self.turns.back().copied().unwrap_or(current);
+        if turn == last || turn == last.opposite() || self.turns.len() >= MAX_QUEUED_TURNS {
+            return false;
+        }
+
+        self.turns.push_back(turn);
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.turns.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
+
+//the edge of the board a wall is on
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Top => "Top",
+            Side::Bottom => "Bottom",
+            Side::Left => "Left",
+            Side::Right => "Right",
+        }
+    }
+}
+
+/*
+    Why a snake died. The world itself only ever runs into walls and into the snake's
+    own body, the other causes are there for host apps that send `KillSnake` themselves.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathCause {
+    Wall(Side),
+    //the segment that was run into, counted from the head which is 0
+    OwnBody(usize),
+    Obstacle,
+    OtherSnake,
+    Timeout,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TickOutcome {
+    Moved,
+    Ate(Cell),
+    Died,
+}
+
+#[derive(Clone, Debug)]
+pub struct SnakeWorld {
+    width: i32,
+    height: i32,
+    mode: GameMode,
+    //front is the head, back is the tail
+    body: VecDeque<Cell>,
+    direction: Direction,
+    food: Vec<Cell>,
+    //segments still to be added for `grow`, one per step
+    growth: u32,
+    alive: bool,
+    //set by the step that killed the snake, `kill` from outside leaves it empty
+    death_cause: Option<DeathCause>,
+    tick: u64,
+}
+
+impl SnakeWorld {
+    //a two cell snake in the middle of the board heading left
+    pub fn new(width: i32, height: i32, mode: GameMode) -> Self {
+        let direction = Direction::Left;
+        let head = Cell::new(width / 2, height / 2);
+
+        let mut body = VecDeque::new();
+        body.push_back(head);
+        body.push_back(head.step(direction.opposite()));
+
+        SnakeWorld {
+            width,
+            height,
+            mode,
+            body,
+            direction,
+            food: Vec::new(),
+            growth: 0,
+            alive: true,
+            death_cause: None,
+            tick: 0,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn head(&self) -> Cell {
+        self.body[0]
+    }
+
+    pub fn body(&self) -> &VecDeque<Cell> {
+        &self.body
+    }
+
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn food(&self) -> &[Cell] {
+        &self.food
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn death_cause(&self) -> Option<DeathCause> {
+        self.death_cause
+    }
+
+    //one segment more than eating gives, the tail stays put on the next step that does not eat
+    pub fn grow(&mut self) {
+        self.growth += 1;
+    }
+
+    pub fn kill(&mut self) {
+        self.alive = false;
+    }
+
+    pub fn in_bounds(&self, cell: Cell) -> bool {
+        cell.x >= 0 && cell.x < self.width && cell.y >= 0 && cell.y < self.height
+    }
+
+    pub fn place_food(&mut self, cell: Cell) {
+        self.food.push(cell);
+    }
+
+    //the snake covers every single cell, there is nowhere left to go
+    pub fn is_complete(&self) -> bool {
+        self.body.len() as i32 >= self.width * self.height
+    }
+
+    //the cell one step from `cell`, folded back onto the board in wrap mode
+    pub fn next_cell(&self, cell: Cell, direction: Direction) -> Cell {
+        let next = cell.step(direction);
+        match self.mode {
+            GameMode::Classic => next,
+            GameMode::Wrap => Cell::new(next.x.rem_euclid(self.width), next.y.rem_euclid(self.height)),
+        }
+    }
+
+    pub fn is_occupied(&self, cell: Cell) -> bool {
+        self.body.contains(&cell) || self.food.contains(&cell)
+    }
+
+    //every cell inside the bounds that holds neither snake nor food, row by row
+    pub fn free_cells(&self) -> Vec<Cell> {
+        let occupied: HashSet<Cell> = self.body.iter().chain(self.food.iter()).copied().collect();
+
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Cell::new(x, y)))
+            .filter(|cell| !occupied.contains(cell))
+            .collect()
+    }
+
+    /*
+        Drops a piece of food on a free cell, every free cell being equally likely.
+        Returns `None` when the board is full and there is nowhere left to put it.
+     */
+    pub fn spawn_food<R: Rng>(&mut self, rng: &mut R) -> Option<Cell> {
+        if self.is_complete() {
+            return None;
+        }
+
+        let cell = *self.free_cells().choose(rng)?;
+        self.place_food(cell);
+        Some(cell)
+    }
+
+    /*
+        Advances the snake one cell. The tail leaves its cell in the same tick the head
+        moves, so following your own tail is allowed, unless the snake is growing and the
+        tail stays put. Eating food grows the snake by one segment on the step it is eaten,
+        growth asked for with `grow` is added on the steps after. A dead world no longer moves.
+     */
+    pub fn step(&mut self, input: Option<Direction>) -> TickOutcome {
+        if !self.alive {
+            return TickOutcome::Died;
+        }
+
+        //turning back into the neck is never a legal move, so it is ignored
+        if let Some(direction) = input {
+            if self.body.len() < 2 || self.next_cell(self.head(), direction) != self.body[1] {
+                self.direction = direction;
+            }
+        }
+
+        self.tick += 1;
+
+        let new_head = self.next_cell(self.head(), self.direction);
+        if !self.in_bounds(new_head) {
+            let side = if new_head.x < 0 {
+                Side::Left
+            } else if new_head.x >= self.width {
+                Side::Right
+            } else if new_head.y < 0 {
+                Side::Bottom
+            } else {
+                Side::Top
+            };
+
+            self.alive = false;
+            self.death_cause = Some(DeathCause::Wall(side));
+            return TickOutcome::Died;
+        }
+
+        let ate = self.food.contains(&new_head);
+        let growing = ate || self.growth > 0;
+        let keep = if growing { self.body.len() } else { self.body.len() - 1 };
+        if let Some(segment) = self.body.iter().take(keep).position(|cell| *cell == new_head) {
+            self.alive = false;
+            self.death_cause = Some(DeathCause::OwnBody(segment));
+            return TickOutcome::Died;
+        }
+
+        if !growing {
+            self.body.pop_back();
+        } else if !ate {
+            self.growth -= 1;
+        }
+        self.body.push_front(new_head);
+
+        if ate {
+            self.food.retain(|cell| *cell != new_head);
+            TickOutcome::Ate(new_head)
+        } else {
+            TickOutcome::Moved
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    //a 10 by 8 board, the snake starts on 5, 4 heading left with its tail on 6, 4
+    fn world(mode: GameMode) -> SnakeWorld {
+        SnakeWorld::new(10, 8, mode)
+    }
+
+    //steps until the snake dies, turning once first
+    fn run_into_wall(turn: Direction) -> SnakeWorld {
+        let mut world = world(GameMode::Classic);
+        world.step(Some(turn));
+        while world.step(None) != TickOutcome::Died {
+            assert!(world.tick() < 20, "never hit a wall");
+        }
+        world
+    }
+
+    //grows the snake to `length` heading left, the body ends up in a straight row
+    fn grown(length: usize) -> SnakeWorld {
+        let mut world = world(GameMode::Classic);
+        while world.length() < length {
+            world.grow();
+            assert_eq!(world.step(None), TickOutcome::Moved);
+        }
+        world
+    }
+
+    #[test]
+    fn wall_death_knows_the_side() {
+        assert_eq!(run_into_wall(Direction::Left).death_cause(), Some(DeathCause::Wall(Side::Left)));
+        assert_eq!(run_into_wall(Direction::Up).death_cause(), Some(DeathCause::Wall(Side::Top)));
+        assert_eq!(run_into_wall(Direction::Down).death_cause(), Some(DeathCause::Wall(Side::Bottom)));
+
+        //right is a reversal from the start, so go up first
+        let mut world = world(GameMode::Classic);
+        world.step(Some(Direction::Up));
+        world.step(Some(Direction::Right));
+        while world.step(None) != TickOutcome::Died {}
+        assert_eq!(world.death_cause(), Some(DeathCause::Wall(Side::Right)));
+    }
+
+    #[test]
+    fn wall_death_leaves_the_head_on_the_board() {
+        let world = run_into_wall(Direction::Left);
+        assert!(!world.is_alive());
+        assert_eq!(world.head(), Cell::new(0, 4));
+        assert_eq!(world.next_cell(world.head(), world.direction()), Cell::new(-1, 4));
+
+        //a dead world stays where it is
+        let mut world = world;
+        let tick = world.tick();
+        assert_eq!(world.step(Some(Direction::Up)), TickOutcome::Died);
+        assert_eq!((world.tick(), world.head()), (tick, Cell::new(0, 4)));
+    }
+
+    #[test]
+    fn reversal_into_the_neck_is_ignored() {
+        let mut world = world(GameMode::Classic);
+        assert_eq!(world.step(Some(Direction::Right)), TickOutcome::Moved);
+        assert_eq!(world.direction(), Direction::Left);
+        assert_eq!(world.head(), Cell::new(4, 4));
+    }
+
+    #[test]
+    fn running_into_the_body_gives_the_segment() {
+        //body in a row from 2, 4 to 6, 4, then a tight turn back into it
+        let mut world = grown(5);
+        world.step(Some(Direction::Down));
+        world.step(Some(Direction::Right));
+        assert_eq!(world.step(Some(Direction::Up)), TickOutcome::Died);
+        assert_eq!(world.death_cause(), Some(DeathCause::OwnBody(3)));
+    }
+
+    #[test]
+    fn following_the_tail_is_allowed() {
+        let mut world = grown(4);
+        world.step(Some(Direction::Down));
+        world.step(Some(Direction::Right));
+        let tail = *world.body().back().unwrap();
+        assert_eq!(world.step(Some(Direction::Up)), TickOutcome::Moved);
+        assert_eq!(world.head(), tail);
+        assert_eq!(world.length(), 4);
+    }
+
+    #[test]
+    fn growing_keeps_the_tail_in_place() {
+        let mut world = grown(4);
+        world.step(Some(Direction::Down));
+        world.step(Some(Direction::Right));
+        world.grow();
+        assert_eq!(world.step(Some(Direction::Up)), TickOutcome::Died);
+        assert_eq!(world.death_cause(), Some(DeathCause::OwnBody(3)));
+    }
+
+    #[test]
+    fn eating_grows_on_the_same_step() {
+        let mut world = world(GameMode::Classic);
+        world.place_food(Cell::new(4, 4));
+        assert_eq!(world.step(None), TickOutcome::Ate(Cell::new(4, 4)));
+        assert!(world.food().is_empty());
+        assert_eq!(world.length(), 3);
+        assert_eq!(*world.body().back().unwrap(), Cell::new(6, 4));
+
+        world.step(None);
+        assert_eq!(world.length(), 3);
+    }
+
+    #[test]
+    fn turn_queue_holds_at_most_three() {
+        let mut turns = TurnQueue::default();
+        assert!(turns.push(Direction::Up, Direction::Left));
+        assert!(turns.push(Direction::Right, Direction::Left));
+        assert!(turns.push(Direction::Down, Direction::Left));
+        assert!(!turns.push(Direction::Left, Direction::Left));
+
+        assert_eq!(turns.pop(), Some(Direction::Up));
+        assert!(turns.push(Direction::Left, Direction::Up));
+    }
+
+    #[test]
+    fn turn_queue_checks_against_the_last_queued_turn() {
+        let mut turns = TurnQueue::default();
+
+        //heading right, left is a reversal
+        assert!(!turns.push(Direction::Left, Direction::Right));
+
+        //but not once up is queued, and down is the reversal of that instead
+        assert!(turns.push(Direction::Up, Direction::Right));
+        assert!(!turns.push(Direction::Down, Direction::Right));
+        assert!(turns.push(Direction::Left, Direction::Right));
+
+        assert_eq!(turns.pop(), Some(Direction::Up));
+        assert_eq!(turns.pop(), Some(Direction::Left));
+        assert_eq!(turns.pop(), None);
+    }
+
+    #[test]
+    fn turn_queue_drops_duplicates() {
+        let mut turns = TurnQueue::default();
+
+        //the snake is already heading that way
+        assert!(!turns.push(Direction::Left, Direction::Left));
+
+        assert!(turns.push(Direction::Up, Direction::Left));
+        assert!(!turns.push(Direction::Up, Direction::Left));
+        assert_eq!(turns.pop(), Some(Direction::Up));
+        assert_eq!(turns.pop(), None);
+    }
+
+    #[test]
+    fn turn_queue_gives_one_turn_per_tick() {
+        let mut world = world(GameMode::Classic);
+        let mut turns = TurnQueue::default();
+
+        //up then right pressed between two ticks, starting on 5, 4 heading left
+        turns.push(Direction::Up, world.direction());
+        turns.push(Direction::Right, world.direction());
+
+        world.step(turns.pop());
+        assert_eq!((world.direction(), world.head()), (Direction::Up, Cell::new(5, 5)));
+        world.step(turns.pop());
+        assert_eq!((world.direction(), world.head()), (Direction::Right, Cell::new(6, 5)));
+        world.step(turns.pop());
+        assert_eq!((world.direction(), world.head()), (Direction::Right, Cell::new(7, 5)));
+    }
+
+    #[test]
+    fn wrap_folds_every_edge() {
+        let world = world(GameMode::Wrap);
+        assert_eq!(world.next_cell(Cell::new(0, 3), Direction::Left), Cell::new(9, 3));
+        assert_eq!(world.next_cell(Cell::new(9, 3), Direction::Right), Cell::new(0, 3));
+        assert_eq!(world.next_cell(Cell::new(4, 7), Direction::Up), Cell::new(4, 0));
+        assert_eq!(world.next_cell(Cell::new(4, 0), Direction::Down), Cell::new(4, 7));
+
+        //classic leaves the board instead
+        let classic = self::world(GameMode::Classic);
+        assert_eq!(classic.next_cell(Cell::new(0, 3), Direction::Left), Cell::new(-1, 3));
+    }
+
+    #[test]
+    fn wrap_never_dies_on_an_edge() {
+        for turn in [Direction::Up, Direction::Down, Direction::Left] {
+            let mut world = world(GameMode::Wrap);
+            world.step(Some(turn));
+            for _ in 0..20 {
+                assert_ne!(world.step(None), TickOutcome::Died);
+                assert!(world.in_bounds(world.head()));
+            }
+        }
+
+        let mut world = world(GameMode::Wrap);
+        world.step(Some(Direction::Up));
+        world.step(Some(Direction::Right));
+        for _ in 0..20 {
+            assert_ne!(world.step(None), TickOutcome::Died);
+        }
+        //twice around the board and back on the same cell
+        assert_eq!(world.head(), Cell::new(6, 5));
+    }
+
+    #[test]
+    fn free_cells_skip_snake_and_food() {
+        let mut world = SnakeWorld::new(3, 2, GameMode::Classic);
+        world.place_food(Cell::new(0, 0));
+
+        let free = world.free_cells();
+        assert_eq!(free.len(), 3);
+        assert!(free.iter().all(|cell| !world.is_occupied(*cell)));
+    }
+
+    #[test]
+    fn full_board_has_nowhere_for_food() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+
+        //3 by 1, the snake takes two cells and the food the last one
+        let mut world = SnakeWorld::new(3, 1, GameMode::Classic);
+        assert_eq!(world.spawn_food(&mut rng), Some(Cell::new(0, 0)));
+        assert!(!world.is_complete());
+        assert_eq!(world.spawn_food(&mut rng), None);
+
+        //growth that is still to come does not fill the board yet
+        let mut world = SnakeWorld::new(3, 1, GameMode::Classic);
+        world.grow();
+        assert!(!world.is_complete());
+        assert_eq!(world.spawn_food(&mut rng), Some(Cell::new(0, 0)));
+    }
+
+    #[test]
+    fn eating_the_last_food_fills_the_board() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+
+        let mut world = SnakeWorld::new(3, 1, GameMode::Classic);
+        world.spawn_food(&mut rng);
+        assert_eq!(world.step(None), TickOutcome::Ate(Cell::new(0, 0)));
+
+        //the win comes with the snake as long as the board, not a step before
+        assert!(world.is_complete());
+        assert!(world.is_alive());
+        assert_eq!(world.length(), 3);
+        assert!(world.free_cells().is_empty());
+        assert_eq!(world.spawn_food(&mut rng), None);
+    }
+}
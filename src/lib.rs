@@ -1,115 +1,417 @@
 #![allow(unused)]
 
 use bevy::{prelude::*, window::WindowCloseRequested};
+use bevy::render::camera::{Camera2d, CameraProjection};
 use bevy::app::AppExit;
 
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 mod resources;
 mod menu;
 mod game;
+mod config;
+mod persistence;
+mod migrate;
+mod paths;
+mod cli;
+mod shared;
+#[cfg(test)]
+mod temp_dir;
+
+pub use cli::LaunchArgs;
+pub use config::{InvalidSetting, Settings, SettingsError};
+pub use paths::GamePaths;
+pub use persistence::{DeathStats, PlayerData, SaveFile};
+pub use shared::text_input::{TextInput, TextSubmitted};
+
+pub use game::{CurrentTick, GameTag, OnTick, RunTimer, TickPhase};
+pub use game::arena::{Arena, ArenaConfig};
+pub use game::difficulty::{Difficulty, TickClock};
+pub use game::ghost::ShowGhost;
+pub use game::food::{BoardFull, Food};
+pub use game::highscore::{HighScoreEvent, LastRun};
+pub use game::leaderboard::{Leaderboard, LeaderboardEntry};
+pub use game::replay::{Replay, ReplayDir, ReplayRecorder, ReplayResult, ReplaySettings, ReplayTurn, Ruleset, RunOutcome};
+pub use game::rng::GameRng;
+pub use game::snake::{AddToSnakeBody, KillSnake, SnakeBody, SnakeHead, SnakeSegments};
+pub use game::world::{Cell, DeathCause, Direction, GameMode, Side, SnakeWorld, TickOutcome};
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum GameState {
     None,
     InGame,
     Dead,
+    Won,
+    Paused,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum AppState {
     Loading,
     Menu,
+    Highscores,
+    Replays,
     Game,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
-pub struct Settings {
-    game_name: String,
-    verison: f32,
-    window_size: WindowSize,
-    resizable: bool,
-    tile_size: i32,
-}
-
-#[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
-pub struct WindowSize {
-    width: f32,
-    height: f32,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
-pub struct PlayerData {
-    highscore: i32
-}
-
-const SETTINGS_PATH: &str = "data/settings.json";
-const GAME_ICON_PATH: &str = "build/windows/game_icon.png";
-const PLAYER_DATA_PATH: &str = "data/player_data.json";
+//space kept free around the board on every side of the window
+const WINDOW_MARGIN: f32 = 26.;
 
 pub struct MainGamePlugin;
 
 impl Plugin for MainGamePlugin {
     fn build(&self, app: &mut App) {
 
-        let settings: Settings = load_json::<Settings>(SETTINGS_PATH);
-        let player_data: PlayerData = load_json::<PlayerData>(PLAYER_DATA_PATH);
+        let (game, paths, settings) = launch(app);
+        let arena = game.config.arena;
 
         //println!("{settings:#?}");
-        //println!("{player_data:#?}");
 
         app        
             .add_startup_system(setup)
             .add_startup_system(set_window_icon)
-            .add_startup_system(resources::create)
+            .add_system_set(SystemSet::on_enter(AppState::Game).with_system(fit_camera))
             
             .insert_resource(WindowDescriptor {
-                width: settings.window_size.width,
-                height: settings.window_size.height,
+                width: settings.window_size.width.max(arena.pixel_width() + 2. * WINDOW_MARGIN),
+                height: settings.window_size.height.max(arena.pixel_height() + 2. * WINDOW_MARGIN),
                 title: String::from(settings.game_name.as_str()),
                 resizable: settings.resizable,
                 ..Default::default()
             })
 
             .add_plugins(DefaultPlugins)
-            .add_plugin(menu::MainMenuPlugin)
+            .add_plugin(game)
+            .insert_resource(settings)
+            .insert_resource(paths)
+            .insert_resource(ClearColor(Color::rgb(0.02, 0.04, 0.08)))
+
+            .add_system(persistence::exit_on_interrupt);
+
+        persistence::catch_interrupt();
+    }
+} 
+
+/*
+    The same game without a window, renderer, icon or fonts, for CI boxes with no display.
+    Sprites and UI are still spawned as plain entities, so tests can drive a run through
+    the states and events and assert on the `SnakeWorld` and components afterwards.
+
+    There is no one to click through the menu, so it always goes straight into a game.
+    Started with `--headless` it reads the settings and keeps saves like the windowed game,
+    `main` leaves the `LaunchArgs` for that. Added by a test there is no command line, so it
+    plays on the default settings and leaves every file alone.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct HeadlessGamePlugin {
+    catch_interrupt: bool,
+}
+
+impl HeadlessGamePlugin {
+    pub fn new() -> Self {
+        HeadlessGamePlugin::default()
+    }
+
+    //Ctrl+C quits through `AppExit` so the data gets saved, the handler is one per process so tests leave it off
+    pub fn catch_interrupt(mut self, catch_interrupt: bool) -> Self {
+        self.catch_interrupt = catch_interrupt;
+        self
+    }
+}
+
+impl Plugin for HeadlessGamePlugin {
+    fn build(&self, app: &mut App) {
+        use bevy::app::ScheduleRunnerSettings;
+        use bevy::hierarchy::HierarchyPlugin;
+        use bevy::input::InputPlugin;
+        use bevy::transform::TransformPlugin;
+        use bevy::window::{ReceivedCharacter, WindowFocused};
+        use std::time::Duration;
+
+        let (game, paths, settings) = if app.world.contains_resource::<LaunchArgs>() {
+            launch(app)
+        } else {
+            let settings = Settings::default();
+            let game = SnakeGamePlugin::from_settings(&settings);
+            (game, GamePaths::resolve(None), settings)
+        };
+
+        app
+            .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(1. / 60.)))
+            .add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            //keeps `Children` up to date, without it the screens leave their text behind when despawned
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_event::<WindowFocused>()
+            .add_event::<ReceivedCharacter>()
+            .add_plugin(game.headless(true).with_menus(false))
+            .insert_resource(settings)
+            .insert_resource(paths);
+
+        if self.catch_interrupt {
+            app.add_system(persistence::exit_on_interrupt);
+            persistence::catch_interrupt();
+        }
+    }
+}
+
+//everything a host app can tune about the game, available as a resource once the plugin is added
+#[derive(Clone, Debug)]
+pub struct SnakeGameConfig {
+    pub arena: ArenaConfig,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    //seconds per tick at the start of a run, replaces the one from the difficulty
+    pub tick_interval: Option<f64>,
+    pub seed: Option<u64>,
+    //without menus the game starts right away and the screens have no menu button
+    pub menus: bool,
+    //no asset server, textures and fonts are left as empty handles
+    pub headless: bool,
+    //where the player data is loaded from and saved to, `None` keeps it in memory only and records no replays
+    pub save_path: Option<PathBuf>,
+    //watched right away instead of starting at the menu
+    pub replay: Option<Replay>,
+    //race the best recorded run of the mode, can be switched on the menu
+    pub ghost: bool,
+}
+
+impl SnakeGameConfig {
+    //a start speed other than the difficulty's own is a different game, those runs are not ranked
+    pub fn custom_pace(&self, difficulty: Difficulty) -> bool {
+        self.tick_interval.is_some_and(|interval| interval != difficulty.pace().interval)
+    }
+}
+
+impl Default for SnakeGameConfig {
+    fn default() -> Self {
+        SnakeGameConfig {
+            arena: ArenaConfig { width: 30, height: 30, tile_size: 16. },
+            mode: GameMode::default(),
+            difficulty: Difficulty::default(),
+            tick_interval: None,
+            seed: None,
+            menus: true,
+            headless: false,
+            save_path: None,
+            replay: None,
+            ghost: false,
+        }
+    }
+}
+
+/*
+    The game on its own, for embedding snake in another Bevy app. The host adds
+    `DefaultPlugins` (or `MinimalPlugins` with `.headless(true)`) and its own camera.
+    Nothing is read from or written to disk unless the host hands it a `save_path`.
+
+        App::new()
+            .add_plugins(DefaultPlugins)
+            .add_plugin(SnakeGamePlugin::new().arena_size(20, 12).mode(GameMode::Wrap).with_menus(false))
+            .run();
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SnakeGamePlugin {
+    config: SnakeGameConfig,
+}
+
+impl SnakeGamePlugin {
+    pub fn new() -> Self {
+        SnakeGamePlugin::default()
+    }
+
+    //in tiles, at least 3 each way or the plugin panics when it is added
+    pub fn arena_size(mut self, width: i32, height: i32) -> Self {
+        self.config.arena.width = width;
+        self.config.arena.height = height;
+        self
+    }
+
+    //in pixels, has to be positive
+    pub fn tile_size(mut self, tile_size: f32) -> Self {
+        self.config.arena.tile_size = tile_size;
+        self
+    }
+
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.config.difficulty = difficulty;
+        self
+    }
+
+    //seconds per move at the start of a run, has to be positive
+    pub fn tick_interval(mut self, seconds: f64) -> Self {
+        self.config.tick_interval = Some(seconds);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn with_menus(mut self, menus: bool) -> Self {
+        self.config.menus = menus;
+        self
+    }
+
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.config.headless = headless;
+        self
+    }
+
+    pub fn save_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config.save_path = path;
+        self
+    }
+
+    pub fn ghost(mut self, ghost: bool) -> Self {
+        self.config.ghost = ghost;
+        self
+    }
+
+    pub fn replay(mut self, replay: Option<Replay>) -> Self {
+        self.config.replay = replay;
+        self
+    }
+
+    pub fn config(&self) -> &SnakeGameConfig {
+        &self.config
+    }
+
+    fn from_settings(settings: &Settings) -> Self {
+        SnakeGamePlugin {
+            config: SnakeGameConfig {
+                arena: ArenaConfig {
+                    width: settings.arena_size.width,
+                    height: settings.arena_size.height,
+                    tile_size: settings.tile_size as f32,
+                },
+                mode: settings.mode,
+                difficulty: settings.difficulty,
+                tick_interval: settings.tick_interval,
+                seed: settings.seed,
+                ghost: settings.ghost,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Plugin for SnakeGamePlugin {
+    fn build(&self, app: &mut App) {
+        let config = self.config.clone();
+
+        //the same rules `Settings::validate` holds the settings file to, a host app passing these in code gets told straight away
+        let arena = config.arena;
+        assert!(
+            arena.width >= config::MIN_ARENA_TILES && arena.height >= config::MIN_ARENA_TILES,
+            "SnakeGamePlugin: the arena has to be at least {0}x{0} tiles, got {1}x{2}",
+            config::MIN_ARENA_TILES, arena.width, arena.height,
+        );
+        assert!(arena.tile_size > 0. && arena.tile_size.is_finite(), "SnakeGamePlugin: the tile size has to be positive, got {}", arena.tile_size);
+        if let Some(interval) = config.tick_interval {
+            assert!(interval > 0. && interval.is_finite(), "SnakeGamePlugin: the tick interval has to be positive, got {interval}");
+        }
+
+        let player_data: PlayerData = match &config.save_path {
+            Some(path) => PlayerData::load(path),
+            None => PlayerData::default(),
+        };
+
+        if config.headless {
+            app.add_startup_system(resources::create_headless);
+        } else {
+            app.add_startup_system(resources::create);
+        }
+
+        if config.menus {
+            app.add_plugin(menu::MainMenuPlugin);
+        }
+
+        let mut arena = config.arena;
+        let mut mode = config.mode;
+        let mut difficulty = config.difficulty;
+        let mut rng = GameRng::new(config.seed);
+        if let Some(replay) = config.replay.clone() {
+            app.insert_resource(game::playback::Playback::new(replay, &mut arena, &mut mode, &mut difficulty, &mut rng));
+        }
+
+        if let Some(path) = &config.save_path {
+            app.insert_resource(SaveFile(path.clone()));
+
+            //replays are kept next to the player data
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            app.insert_resource(ReplayDir(dir.join(game::replay::REPLAY_DIR_NAME)));
+        }
+
+        app
+            .add_plugin(shared::SharedPlugin)
             .add_plugin(game::GamePlugin)
             
             .add_state(AppState::Loading)
             .add_state(GameState::None)
 
-            .insert_resource(settings)
+            .insert_resource(rng)
+            .insert_resource(arena)
+            .insert_resource(mode)
+            .insert_resource(difficulty)
+            .insert_resource(ShowGhost(config.ghost))
             .insert_resource(player_data)
-            .insert_resource(ClearColor(Color::rgb(0.02, 0.04, 0.08)))
+            .insert_resource(config)
 
-            .add_system(on_window_close);
+            .add_system_to_stage(CoreStage::Last, persistence::save_on_change)
+            .add_system_to_stage(CoreStage::Last, persistence::save_on_exit);
     }
-} 
+}
 
-fn load_json<T>(path: &str) -> T where T: serde::de::DeserializeOwned {
-    let json_file_path = Path::new(path);
-    let file = File::open(json_file_path);
-    let reader = BufReader::new(file.unwrap());
-    let json_data: T = serde_json::from_reader(reader).expect("JSON for was not formatted correctly!");
+/*
+    Everything both app plugins start from: the command line `main` left as a resource
+    (none when the plugin is added by some other app), where the files are kept, the
+    settings file with the command line put on top, and the game plugin made from them.
+ */
+fn launch(app: &mut App) -> (SnakeGamePlugin, GamePaths, Settings) {
+    let args = app.world.get_resource::<LaunchArgs>().cloned().unwrap_or_default();
+
+    let paths = GamePaths::resolve(args.data_dir.clone());
+    paths.adopt_old_player_data();
+
+    let mut settings: Settings = match &args.config {
+        Some(path) => Settings::load(path, &paths.bundled_settings(), false),
+        None => Settings::load(&paths.settings(), &paths.bundled_settings(), true),
+    };
+    args.apply(&mut settings);
 
-    json_data
+    let replay = args.replay.as_ref().and_then(|path| match Replay::load(path) {
+        Ok(replay) => Some(replay),
+        Err(error) => {
+            eprintln!("{}: could not load the replay: {error}, starting a normal game", path.display());
+            None
+        }
+    });
+
+    let game = SnakeGamePlugin::from_settings(&settings)
+        .save_path(Some(paths.player_data()))
+        .with_menus(!args.skip_menu)
+        .replay(replay);
+
+    (game, paths, settings)
 }
 
 fn setup(
-    mut commands: Commands
+    mut commands: Commands,
+    arena: Res<game::arena::ArenaConfig>,
 ) {
     commands.spawn_bundle(UiCameraBundle::default());
 
     //camera
     let mut camera = OrthographicCameraBundle::new_2d();
-     camera.transform.translation = Vec3::new( 240., 240., 2.);
+     camera.transform.translation = arena.center().extend(2.);
     
      camera.orthographic_projection.top = 1.0;
     camera.orthographic_projection.bottom = -1.0;
@@ -117,6 +419,28 @@ fn setup(
     commands.spawn_bundle(camera);
 }
 
+//centers the board, and zooms out when it is bigger than the window, which a replay from other settings can be
+fn fit_camera(
+    arena: Res<game::arena::ArenaConfig>,
+    windows: Res<Windows>,
+    mut camera_query: Query<(&mut Camera, &mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let room = Vec2::new(window.width(), window.height()) - 2. * WINDOW_MARGIN;
+    let scale = (arena.pixel_width() / room.x).max(arena.pixel_height() / room.y).max(1.);
+
+    for (mut camera, mut transform, mut projection) in camera_query.iter_mut() {
+        transform.translation = arena.center().extend(transform.translation.z);
+        projection.scale = scale;
+        //only worked out again by bevy when the window changes
+        camera.projection_matrix = projection.get_projection_matrix();
+    }
+}
+
 /*
     A hacky way to set window icon from: https://bevy-cheatbook.github.io/window/icon.html
     winit = "0.26.1"
@@ -124,7 +448,7 @@ fn setup(
  */
 use bevy::winit::WinitWindows;
 
-fn set_window_icon(windows: NonSend<WinitWindows>) {
+fn set_window_icon(windows: NonSend<WinitWindows>, paths: Res<GamePaths>) {
     use bevy::window::WindowId;
     use winit::window::Icon;
     use image::GenericImageView;
@@ -134,9 +458,14 @@ fn set_window_icon(windows: NonSend<WinitWindows>) {
     // here we use the `image` crate to load our icon data from a png file
     // this is not a very bevy-native solution, but it will do
     let (icon_rgba, icon_width, icon_height) = {
-        let image = image::open(GAME_ICON_PATH)
-            .expect("Failed to open icon path")
-            .into_rgba8();
+        //the window keeps the default icon of the platform
+        let image = match image::open(paths.icon()) {
+            Ok(image) => image.into_rgba8(),
+            Err(error) => {
+                warn!("{}: could not open the window icon: {error}", paths.icon().display());
+                return;
+            }
+        };
         let (width, height) = image.dimensions();
         let rgba = image.into_raw();
         (rgba, width, height)
@@ -146,16 +475,3 @@ fn set_window_icon(windows: NonSend<WinitWindows>) {
 
     primary.set_window_icon(Some(icon));
 }
-
-fn on_window_close(
-    app_exit_event: EventReader<AppExit>,
-    mut player_data: ResMut<PlayerData>
-) {
-    if app_exit_event.is_empty() { return }
-
-    std::fs::write(
-        Path::new(PLAYER_DATA_PATH),
-        serde_json::to_string_pretty(player_data.as_ref()).unwrap(),
-    ).unwrap();
-
-}
\ No newline at end of file
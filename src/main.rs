@@ -2,10 +2,20 @@
 
 use bevy::prelude::*;
 
-use snake::MainGamePlugin;
+use snake::{HeadlessGamePlugin, LaunchArgs, MainGamePlugin};
 
 fn main() { 
-    App::new()
-        .add_plugin(MainGamePlugin)
-        .run();
+    let args = LaunchArgs::from_env();
+    let headless = args.headless;
+
+    let mut app = App::new();
+    app.insert_resource(args);
+
+    if headless {
+        app.add_plugin(HeadlessGamePlugin::new().catch_interrupt(true));
+    } else {
+        app.add_plugin(MainGamePlugin);
+    }
+
+    app.run();
 }
\ No newline at end of file
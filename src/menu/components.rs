@@ -1,4 +1,16 @@
 use bevy::prelude::*;
 
 #[derive(Component)]
-pub struct MenuTag;
\ No newline at end of file
+pub struct MenuTag;
+
+//the label of the button that picks the game mode
+#[derive(Component)]
+pub struct ModeText;
+
+//the label of the button that picks the difficulty
+#[derive(Component)]
+pub struct DifficultyText;
+
+//the label of the button that turns the ghost on and off
+#[derive(Component)]
+pub struct GhostText;
\ No newline at end of file
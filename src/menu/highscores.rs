@@ -0,0 +1,270 @@
+use bevy::prelude::*;
+
+use crate::*;
+use crate::game::leaderboard::{self, LEADERBOARD_SIZE};
+
+#[derive(Component)]
+pub struct HighscoresTag;
+
+#[derive(Component)]
+pub struct BackButton;
+
+//cycles the difficulty the list is shown for
+#[derive(Component)]
+pub struct DifficultyButton;
+
+//the difficulty whose leaderboard is shown, starts on the one picked on the menu
+#[derive(Default)]
+pub struct ShownDifficulty(Difficulty);
+
+//widths of the rank, name, score, length, time and date columns
+const COLUMN_WIDTHS: [f32; 6] = [30., 120., 60., 60., 60., 100.];
+
+pub fn setup(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    player_data: Res<PlayerData>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    last_run: Res<LastRun>,
+    mut shown: ResMut<ShownDifficulty>,
+) {
+    shown.0 = *difficulty;
+    spawn_list(&mut commands, &font_assets, &player_data, *mode, shown.0, &last_run);
+}
+
+fn spawn_list(
+    commands: &mut Commands,
+    font_assets: &resources::GameFonts,
+    player_data: &PlayerData,
+    mode: GameMode,
+    difficulty: Difficulty,
+    last_run: &LastRun,
+) {
+    let button_style: Style = Style {
+        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+        margin: Rect::all(Val::Px(8.)),
+        padding: Rect::all(Val::Px(8.)),
+        align_content: AlignContent::Center,
+        align_items: AlignItems::Center,
+        align_self: AlignSelf::Center,
+        justify_content: JustifyContent::Center,
+        ..Default::default()
+    };
+
+    let button_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 24.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
+    let title_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 40.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let header_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.6, 0.6, 0.6),
+    };
+
+    let row_txt_style : TextStyle = TextStyle {
+        font: font_assets.regular.clone(),
+        font_size: 14.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    //the run that just placed stands out
+    let last_txt_style : TextStyle = TextStyle {
+        color: Color::rgb(1., 0.8, 0.2),
+        ..row_txt_style.clone()
+    };
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect::all(Val::Px(0.)),
+            margin: Rect::all(Val::Px(16.)),
+            padding: Rect::all(Val::Px(16.)),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_content: AlignContent::Center,
+            align_items: AlignItems::Center,
+            align_self: AlignSelf::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(HighscoresTag)
+    .id();
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "HIGHSCORES".to_string(),
+                style: title_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: format!("{} - {}", mode.name(), difficulty.name()).to_uppercase(),
+                style: header_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    let entries = player_data.leaderboard(mode, difficulty);
+
+    let header = ["#", "NAME", "SCORE", "LENGTH", "TIME", "DATE"].map(String::from);
+    menu::spawn_table_row(commands, Some(container), header, COLUMN_WIDTHS, &header_txt_style);
+
+    for (rank, entry) in entries.iter().enumerate().take(LEADERBOARD_SIZE) {
+        let row = [
+            format!("{}", rank + 1),
+            entry.name.clone(),
+            entry.score.to_string(),
+            entry.length.to_string(),
+            leaderboard::duration_string(entry.duration),
+            leaderboard::date_string(entry.timestamp),
+        ];
+
+        let is_last_run = last_run.mode == mode && last_run.difficulty == difficulty && last_run.rank == Some(rank);
+        let style = if is_last_run { &last_txt_style } else { &row_txt_style };
+        menu::spawn_table_row(commands, Some(container), row, COLUMN_WIDTHS, style);
+    }
+
+    if entries.is_empty() {
+        commands.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: "NO RUNS YET".to_string(),
+                    style: row_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container));
+    }
+
+    //every run of every mode counts here, not only the ones on the leaderboard
+    let deaths = player_data.death_stats();
+    if deaths.total() > 0 {
+        commands.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: format!(
+                        "DEATHS {}   WALLS {}   OWN BODY {}",
+                        deaths.total(), deaths.wall_total(), deaths.own_body,
+                    ),
+                    style: header_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container));
+    }
+
+    let footer = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .id();
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: difficulty.name().to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    })
+    .insert(Parent(footer))
+    .insert(DifficultyButton);
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: "Back".to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    })
+    .insert(Parent(footer))
+    .insert(BackButton);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn button_update(
+    mut commands: Commands,
+    back_query: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    difficulty_query: Query<&Interaction, (Changed<Interaction>, With<DifficultyButton>)>,
+    list_query: Query<Entity, With<HighscoresTag>>,
+    input: Res<Input<KeyCode>>,
+    font_assets: Res<resources::GameFonts>,
+    player_data: Res<PlayerData>,
+    mode: Res<GameMode>,
+    last_run: Res<LastRun>,
+    mut shown: ResMut<ShownDifficulty>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if back_query.iter().any(|interaction| *interaction == Interaction::Clicked) || input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::Menu);
+        return;
+    }
+
+    //the difficulties go round, left goes the other way
+    let turns = if difficulty_query.iter().any(|interaction| *interaction == Interaction::Clicked) || input.just_pressed(KeyCode::Right) {
+        1
+    } else if input.just_pressed(KeyCode::Left) {
+        3
+    } else {
+        return;
+    };
+
+    for _ in 0..turns {
+        shown.0 = shown.0.next();
+    }
+
+    for entity in list_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_list(&mut commands, &font_assets, &player_data, *mode, shown.0, &last_run);
+}
@@ -1,14 +1,25 @@
 use bevy::prelude::*;
 use bevy::app::AppExit;
+use bevy::ui::FocusPolicy;
 
 use crate::*;
 use crate::resources::*;
+use crate::game::world::GameMode;
+use crate::game::difficulty::Difficulty;
+use crate::game::ghost::ShowGhost;
 
 mod components;
+mod highscores;
+mod replays;
 
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    Mode,
+    Difficulty,
+    Highscores,
+    Replays,
+    Ghost,
     Quit
 }
 
@@ -26,14 +37,37 @@ impl Plugin for MainMenuPlugin {
                 .with_system(button_effects)
                 .with_system(button_update))
             .add_system_set(SystemSet::on_exit(AppState::Menu)
-                        .with_system(despawn_screen::<components::MenuTag>));
+                        .with_system(despawn_screen::<components::MenuTag>))
+
+            .init_resource::<highscores::ShownDifficulty>()
+            .add_system_set(SystemSet::on_enter(AppState::Highscores)
+                .with_system(highscores::setup))
+            .add_system_set(SystemSet::on_update(AppState::Highscores)
+                .with_system(button_effects)
+                .with_system(highscores::button_update))
+            .add_system_set(SystemSet::on_exit(AppState::Highscores)
+                .with_system(despawn_screen::<highscores::HighscoresTag>))
+
+            .init_resource::<replays::ReplayPage>()
+            //coming back from watching one, the board is still up
+            .add_system_set(SystemSet::on_enter(AppState::Replays)
+                .with_system(despawn_screen::<game::GameTag>)
+                .with_system(replays::setup))
+            .add_system_set(SystemSet::on_update(AppState::Replays)
+                .with_system(button_effects)
+                .with_system(replays::button_update))
+            .add_system_set(SystemSet::on_exit(AppState::Replays)
+                .with_system(despawn_screen::<replays::ReplaysTag>));
     }
 }
 
 fn setup(
     mut commands: Commands,
     font_assets: Res<resources::GameFonts>,
-    player_data: Res<PlayerData>
+    player_data: Res<PlayerData>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    show_ghost: Res<ShowGhost>,
 ) { 
     let button_style: Style = Style {
         size: Size::new(Val::Px(120.0), Val::Px(50.0)),
@@ -109,6 +143,117 @@ fn setup(
     .insert(Parent(container))
     .insert(MenuButtonAction::Play);
 
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: mode.name().to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(components::ModeText);
+    })
+    .insert(Parent(container))
+    .insert(MenuButtonAction::Mode);
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: difficulty.name().to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(components::DifficultyText);
+    })
+    .insert(Parent(container))
+    .insert(MenuButtonAction::Difficulty);
+
+    //scores, replays and the ghost side by side, the menu is too tall for the window otherwise
+    let row = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .id();
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: "Scores".to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    })
+    .insert(Parent(row))
+    .insert(MenuButtonAction::Highscores);
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: "Replays".to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    })
+    .insert(Parent(row))
+    .insert(MenuButtonAction::Replays);
+
+    commands.spawn_bundle(ButtonBundle {
+        style: button_style.clone(),
+        ..Default::default()
+    })
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: ghost_label(*show_ghost).to_string(),
+                    style: button_txt_style.clone(),
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(components::GhostText);
+    })
+    .insert(Parent(row))
+    .insert(MenuButtonAction::Ghost);
+
     commands.spawn_bundle(ButtonBundle {
         style: button_style.clone(),
         ..Default::default()
@@ -130,6 +275,11 @@ fn setup(
 
 }
 
+fn ghost_label(show_ghost: ShowGhost) -> &'static str {
+    //"no ghost" does not fit on the button
+    if show_ghost.0 { "Ghost" } else { "Solo" }
+}
+
 fn update() {
     
 }
@@ -154,24 +304,58 @@ fn button_effects(
     }
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn button_update(
     interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
     mut app_exit_event: EventWriter<AppExit>,
     mut app_state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut show_ghost: ResMut<ShowGhost>,
+    mut mode_text_query: Query<&mut Text, (With<components::ModeText>, Without<components::DifficultyText>, Without<components::GhostText>)>,
+    mut difficulty_text_query: Query<&mut Text, (With<components::DifficultyText>, Without<components::GhostText>)>,
+    mut ghost_text_query: Query<&mut Text, With<components::GhostText>>,
 ) {
     let mut quit_button = || {
         app_exit_event.send(AppExit);
     };
 
-    let mut play_button = || {
-        app_state.set(AppState::Game).unwrap();
+    //play, the highscores and the replays all leave the menu for another state
+    let mut go_to = |state: AppState| {
+        app_state.set(state).unwrap();
+    };
+
+    let mut mode_button = || {
+        *mode = mode.next();
+        for mut text in mode_text_query.iter_mut() {
+            text.sections[0].value = mode.name().to_string();
+        }
+    };
+
+    let mut difficulty_button = || {
+        *difficulty = difficulty.next();
+        for mut text in difficulty_text_query.iter_mut() {
+            text.sections[0].value = difficulty.name().to_string();
+        }
+    };
+
+    let mut ghost_button = || {
+        show_ghost.0 = !show_ghost.0;
+        for mut text in ghost_text_query.iter_mut() {
+            text.sections[0].value = ghost_label(*show_ghost).to_string();
+        }
     };
 
     for (interaction, menu_button_action) in interaction_query.iter() {
         if *interaction == Interaction::Clicked {
             match menu_button_action {
                 MenuButtonAction::Quit => quit_button(),
-                MenuButtonAction::Play => play_button()
+                MenuButtonAction::Play => go_to(AppState::Game),
+                MenuButtonAction::Mode => mode_button(),
+                MenuButtonAction::Difficulty => difficulty_button(),
+                MenuButtonAction::Highscores => go_to(AppState::Highscores),
+                MenuButtonAction::Replays => go_to(AppState::Replays),
+                MenuButtonAction::Ghost => ghost_button()
             }
         }
     }
@@ -182,4 +366,60 @@ fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands
     for entity in to_despawn.iter() {
         commands.entity(entity).despawn_recursive();
     }
-}
\ No newline at end of file
+}
+/*
+    One line of a table on the highscores or the replays screen, every cell a fixed width
+    so the columns line up. The rows of the replay list sit inside buttons, so nothing in
+    a row keeps the click from what it is in.
+ */
+fn spawn_table_row<const N: usize>(
+    commands: &mut Commands,
+    parent: Option<Entity>,
+    cells: [String; N],
+    widths: [f32; N],
+    style: &TextStyle,
+) -> Entity {
+    let row = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            margin: Rect::all(Val::Px(2.)),
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        focus_policy: FocusPolicy::Pass,
+        ..Default::default()
+    })
+    .id();
+
+    if let Some(parent) = parent {
+        commands.entity(row).insert(Parent(parent));
+    }
+
+    for (value, width) in cells.into_iter().zip(widths) {
+        commands.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(width), Val::Auto),
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value,
+                        style: style.clone(),
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        })
+        .insert(Parent(row));
+    }
+
+    row
+}
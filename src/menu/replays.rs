@@ -0,0 +1,300 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::*;
+use crate::game::leaderboard;
+use crate::game::playback::Playback;
+
+#[derive(Component)]
+pub struct ReplaysTag;
+
+#[derive(Component)]
+pub enum ReplaysButton {
+    Watch(PathBuf),
+    Previous,
+    Next,
+    Back,
+}
+
+//the page of the list that is shown, kept for when the player comes back from watching
+#[derive(Default)]
+pub struct ReplayPage(usize);
+
+const PAGE_SIZE: usize = 8;
+
+//widths of the date, mode, difficulty, score, length, time and result columns
+const COLUMN_WIDTHS: [f32; 7] = [90., 60., 60., 50., 50., 50., 70.];
+
+pub fn setup(
+    mut commands: Commands,
+    font_assets: Res<resources::GameFonts>,
+    replay_dir: Option<Res<ReplayDir>>,
+    mut page: ResMut<ReplayPage>,
+) {
+    spawn_list(&mut commands, &font_assets, replay_dir.as_deref(), &mut page);
+}
+
+//every replay in the dir, newest first, the file names start with when the run ended
+fn replay_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    files.sort();
+    files.reverse();
+    files
+}
+
+fn spawn_list(
+    commands: &mut Commands,
+    font_assets: &resources::GameFonts,
+    replay_dir: Option<&ReplayDir>,
+    page: &mut ReplayPage,
+) {
+    let button_style: Style = Style {
+        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+        margin: Rect::all(Val::Px(8.)),
+        padding: Rect::all(Val::Px(8.)),
+        align_content: AlignContent::Center,
+        align_items: AlignItems::Center,
+        align_self: AlignSelf::Center,
+        justify_content: JustifyContent::Center,
+        ..Default::default()
+    };
+
+    let page_button_style: Style = Style {
+        size: Size::new(Val::Px(50.0), Val::Px(50.0)),
+        ..button_style.clone()
+    };
+
+    let button_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 24.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
+    let title_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 40.0,
+        color: Color::rgb(1., 1., 1.),
+    };
+
+    let header_txt_style : TextStyle = TextStyle {
+        font: font_assets.bold.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.6, 0.6, 0.6),
+    };
+
+    //the rows are buttons, so their text is the button blue
+    let row_txt_style : TextStyle = TextStyle {
+        font: font_assets.regular.clone(),
+        font_size: 14.0,
+        color: Color::rgb(0.2, 0.2, 0.9),
+    };
+
+    let container = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect::all(Val::Px(0.)),
+            margin: Rect::all(Val::Px(16.)),
+            padding: Rect::all(Val::Px(16.)),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_content: AlignContent::Center,
+            align_items: AlignItems::Center,
+            align_self: AlignSelf::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(ReplaysTag)
+    .id();
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: "REPLAYS".to_string(),
+                style: title_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+
+    let files = replay_dir.map(|replay_dir| replay_files(&replay_dir.0)).unwrap_or_default();
+    let pages = files.len().div_ceil(PAGE_SIZE).max(1);
+    page.0 = page.0.min(pages - 1);
+
+    let header = ["DATE", "MODE", "LEVEL", "SCORE", "LENGTH", "TIME", "RESULT"].map(String::from);
+    menu::spawn_table_row(commands, Some(container), header, COLUMN_WIDTHS, &header_txt_style);
+
+    for path in files.iter().skip(page.0 * PAGE_SIZE).take(PAGE_SIZE) {
+        //a file that cannot be read is left out of the list, the reason goes to the log
+        let replay = match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(error) => {
+                warn!("{}: could not load the replay: {error}", path.display());
+                continue;
+            }
+        };
+
+        let (outcome, score, length, duration, timestamp) = match replay.result {
+            Some(result) => (format!("{:?}", result.outcome).to_uppercase(), result.score, result.length, result.duration, result.timestamp),
+            None => ("-".to_string(), 0, 0, 0., 0),
+        };
+
+        let row = [
+            leaderboard::date_string(timestamp),
+            replay.ruleset.mode.name().to_uppercase(),
+            replay.settings.difficulty.name().to_uppercase(),
+            score.to_string(),
+            length.to_string(),
+            leaderboard::duration_string(duration),
+            outcome,
+        ];
+
+        let button = commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(2.)),
+                padding: Rect::all(Val::Px(4.)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container))
+        .insert(ReplaysButton::Watch(path.clone()))
+        .id();
+
+        menu::spawn_table_row(commands, Some(button), row, COLUMN_WIDTHS, &row_txt_style);
+    }
+
+    if files.is_empty() {
+        commands.spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: "NO REPLAYS YET".to_string(),
+                    style: TextStyle { color: Color::rgb(1., 1., 1.), ..row_txt_style.clone() },
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Parent(container));
+    }
+
+    let footer = commands
+    .spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: UiColor(Color::NONE),
+        ..Default::default()
+    })
+    .insert(Parent(container))
+    .id();
+
+    let buttons = [
+        ("<", ReplaysButton::Previous, &page_button_style),
+        ("Back", ReplaysButton::Back, &button_style),
+        (">", ReplaysButton::Next, &page_button_style),
+    ];
+
+    for (label, action, style) in buttons {
+        commands.spawn_bundle(ButtonBundle {
+            style: style.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: label.to_string(),
+                        style: button_txt_style.clone(),
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        })
+        .insert(Parent(footer))
+        .insert(action);
+    }
+
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: format!("PAGE {}/{}", page.0 + 1, pages),
+                style: header_txt_style.clone(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Parent(container));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn button_update(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ReplaysButton), Changed<Interaction>>,
+    list_query: Query<Entity, With<ReplaysTag>>,
+    input: Res<Input<KeyCode>>,
+    font_assets: Res<resources::GameFonts>,
+    replay_dir: Option<Res<ReplayDir>>,
+    mut page: ResMut<ReplayPage>,
+    mut arena: ResMut<ArenaConfig>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::Menu);
+        return;
+    }
+
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked { continue }
+
+        match button {
+            ReplaysButton::Watch(path) => match Replay::load(path) {
+                Ok(replay) => {
+                    let playback = Playback::new(replay, &mut arena, &mut mode, &mut difficulty, &mut rng);
+                    commands.insert_resource(playback);
+                    app_state.set(AppState::Game);
+                    return;
+                }
+                Err(error) => warn!("{}: could not load the replay: {error}", path.display()),
+            },
+            ReplaysButton::Previous | ReplaysButton::Next => {
+                page.0 = match button {
+                    ReplaysButton::Previous => page.0.saturating_sub(1),
+                    _ => page.0 + 1,
+                };
+
+                for entity in list_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                spawn_list(&mut commands, &font_assets, replay_dir.as_deref(), &mut page);
+                return;
+            }
+            ReplaysButton::Back => {
+                app_state.set(AppState::Menu);
+                return;
+            }
+        }
+    }
+}
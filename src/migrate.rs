@@ -0,0 +1,186 @@
+use serde_json::{Map, Value};
+
+/*
+    Upgrades old save and settings files before they are turned into structs. A file
+    stores its layout as `SchemaVersion`, a file from before versioning counts as 0.
+    Each migration takes the raw JSON one version up, so a file of any age walks the
+    whole chain and the structs only ever have to read the newest layout.
+
+    A file from a newer version than the chain knows is not touched. Its fields this
+    version does not know would be lost on the next save, so the caller either refuses
+    it or reads it without ever writing it back.
+
+    Adding a field with a serde default does not need a migration, only renaming,
+    moving or reinterpreting something that is already in people's files does.
+ */
+
+pub const VERSION_KEY: &str = "SchemaVersion";
+
+//`chain[n]` upgrades a file from version n to n + 1
+pub type Migration = fn(&mut Map<String, Value>);
+
+//the version the file was at before, or an error when it is not a JSON object
+pub fn migrate(value: &mut Value, chain: &[Migration]) -> Result<u32, String> {
+    let object = value.as_object_mut().ok_or("expected a JSON object at the top level")?;
+
+    let from = match object.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version.as_u64()
+            .map(|version| version as u32)
+            .ok_or(format!("{VERSION_KEY} has to be a whole number"))?,
+    };
+
+    //left as it is, a version above `chain.len()` tells the caller it must not write the file back
+    let current = chain.len() as u32;
+    if from > current {
+        return Ok(from);
+    }
+
+    for migration in &chain[from as usize..] {
+        migration(object);
+    }
+    object.insert(VERSION_KEY.to_string(), Value::from(current));
+
+    Ok(from)
+}
+
+pub mod settings {
+    use super::*;
+
+    pub const CHAIN: &[Migration] = &[v0_to_v1];
+
+    //before the arena could be sized the board was always 480 pixels across
+    const OLD_ARENA_PIXELS: i64 = 480;
+
+    /*
+        The misspelled `Verison` was never read by anything and goes away. A file from
+        before `ArenaSize` gets the board it always had instead of the new default.
+     */
+    fn v0_to_v1(object: &mut Map<String, Value>) {
+        object.remove("Verison");
+
+        if object.contains_key("ArenaSize") { return }
+
+        let tile_size = object.get("TileSize").and_then(Value::as_i64).unwrap_or(16);
+        if tile_size > 0 {
+            let tiles = OLD_ARENA_PIXELS / tile_size;
+            object.insert("ArenaSize".to_string(), serde_json::json!({ "Width": tiles, "Height": tiles }));
+        }
+    }
+}
+
+pub mod player_data {
+    use super::*;
+
+    pub const CHAIN: &[Migration] = &[v0_to_v1, v1_to_v2];
+
+    //the single `Highscore` from before difficulties existed becomes the Normal one
+    fn v0_to_v1(object: &mut Map<String, Value>) {
+        let legacy = match object.remove("Highscore").and_then(|score| score.as_i64()) {
+            Some(score) => score,
+            None => return,
+        };
+
+        let highscores = object.entry("Highscores")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(highscores) = highscores.as_object_mut() {
+            let normal = highscores.get("Normal").and_then(Value::as_i64).unwrap_or(0);
+            highscores.insert("Normal".to_string(), Value::from(normal.max(legacy)));
+        }
+    }
+
+    /*
+        There was one leaderboard per mode, every difficulty in it. It is split into one per
+        difficulty by the `Difficulty` every entry already had, the order within stays.
+     */
+    fn v1_to_v2(object: &mut Map<String, Value>) {
+        let leaderboards = match object.get_mut("Leaderboards").and_then(Value::as_object_mut) {
+            Some(leaderboards) => leaderboards,
+            None => return,
+        };
+
+        for leaderboard in leaderboards.values_mut() {
+            let entries = match leaderboard.as_array() {
+                Some(entries) => entries.clone(),
+                None => continue,
+            };
+
+            let mut by_difficulty = Map::new();
+            for entry in entries {
+                let difficulty = entry.get("Difficulty").and_then(Value::as_str).unwrap_or("Normal").to_string();
+                let list = by_difficulty.entry(difficulty).or_insert_with(|| Value::Array(Vec::new()));
+                if let Some(list) = list.as_array_mut() {
+                    list.push(entry);
+                }
+            }
+            *leaderboard = Value::Object(by_difficulty);
+        }
+    }
+}
+
+pub mod replay {
+    use super::*;
+
+    pub const CHAIN: &[Migration] = &[v0_to_v1];
+
+    //replays were versioned from the start, a file without a version is taken as the first layout
+    fn v0_to_v1(_object: &mut Map<String, Value>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn old_highscore_becomes_the_normal_one() {
+        let mut value = json!({ "Highscore": 1 });
+        assert_eq!(migrate(&mut value, player_data::CHAIN), Ok(0));
+
+        assert_eq!(value, json!({ "SchemaVersion": 2, "Highscores": { "Normal": 1 } }));
+    }
+
+    #[test]
+    fn old_settings_keep_their_board() {
+        let mut value = json!({ "Verison": "0.1", "TileSize": 20 });
+        assert_eq!(migrate(&mut value, settings::CHAIN), Ok(0));
+
+        assert_eq!(value, json!({
+            "SchemaVersion": 1,
+            "TileSize": 20,
+            "ArenaSize": { "Width": 24, "Height": 24 },
+        }));
+    }
+
+    #[test]
+    fn leaderboards_split_by_difficulty() {
+        let mut value = json!({
+            "SchemaVersion": 1,
+            "Leaderboards": { "Classic": [
+                { "Score": 9, "Difficulty": "Hard" },
+                { "Score": 5, "Difficulty": "Normal" },
+                { "Score": 3, "Difficulty": "Hard" },
+            ] },
+        });
+        assert_eq!(migrate(&mut value, player_data::CHAIN), Ok(1));
+
+        assert_eq!(value["Leaderboards"]["Classic"], json!({
+            "Hard": [{ "Score": 9, "Difficulty": "Hard" }, { "Score": 3, "Difficulty": "Hard" }],
+            "Normal": [{ "Score": 5, "Difficulty": "Normal" }],
+        }));
+    }
+
+    #[test]
+    fn newer_files_are_left_alone() {
+        let file = json!({ "SchemaVersion": 7, "Highscore": 4, "SomethingNew": true });
+        let mut value = file.clone();
+        assert_eq!(migrate(&mut value, player_data::CHAIN), Ok(7));
+        assert_eq!(value, file);
+    }
+
+    #[test]
+    fn only_objects_with_whole_versions() {
+        assert!(migrate(&mut json!([1, 2]), settings::CHAIN).is_err());
+        assert!(migrate(&mut json!({ "SchemaVersion": "2" }), settings::CHAIN).is_err());
+    }
+}
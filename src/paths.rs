@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+/*
+    Where the game reads and writes its files, independent of the working directory.
+    The player's own settings and saves live in the platform config and data dirs
+    (`~/.config/snake` and `~/.local/share/snake` on Linux), the files shipped with the
+    game are found next to the executable the same way Bevy finds `assets`, and are
+    only ever read. `--data-dir <path>` or `SNAKE_DATA_DIR` keeps everything in one
+    directory instead, for portable installs and tests.
+ */
+#[derive(Clone, Debug)]
+pub struct GamePaths {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+    //the install, holding `assets`, `data` and `build`
+    pub bundle_dir: PathBuf,
+}
+
+pub const DATA_DIR_ENV: &str = "SNAKE_DATA_DIR";
+
+const SETTINGS_FILE: &str = "settings.json";
+const PLAYER_DATA_FILE: &str = "player_data.json";
+const BUNDLED_SETTINGS_PATH: &str = "data/settings.json";
+//where the player data was kept before it moved to the data dir
+const OLD_PLAYER_DATA_PATH: &str = "data/player_data.json";
+const GAME_ICON_PATH: &str = "build/windows/game_icon.png";
+
+impl GamePaths {
+    //`data_dir` is the one from the command line, it goes before the environment
+    pub fn resolve(data_dir: Option<PathBuf>) -> Self {
+        let bundle_dir = bundle_dir();
+
+        if let Some(dir) = data_dir.or_else(|| std::env::var_os(DATA_DIR_ENV).map(PathBuf::from)) {
+            return GamePaths::in_dir(dir, bundle_dir);
+        }
+
+        match ProjectDirs::from("", "", "Snake") {
+            Some(dirs) => GamePaths {
+                config_dir: dirs.config_dir().to_path_buf(),
+                data_dir: dirs.data_dir().to_path_buf(),
+                bundle_dir,
+            },
+            //no home directory to speak of, keep everything with the install like before
+            None => GamePaths::in_dir(bundle_dir.join("data"), bundle_dir),
+        }
+    }
+
+    pub fn in_dir(dir: PathBuf, bundle_dir: PathBuf) -> Self {
+        GamePaths {
+            config_dir: dir.clone(),
+            data_dir: dir,
+            bundle_dir,
+        }
+    }
+
+    pub fn settings(&self) -> PathBuf {
+        self.config_dir.join(SETTINGS_FILE)
+    }
+
+    pub fn bundled_settings(&self) -> PathBuf {
+        self.bundle_dir.join(BUNDLED_SETTINGS_PATH)
+    }
+
+    pub fn player_data(&self) -> PathBuf {
+        self.data_dir.join(PLAYER_DATA_FILE)
+    }
+
+    pub fn icon(&self) -> PathBuf {
+        self.bundle_dir.join(GAME_ICON_PATH)
+    }
+
+    //brings highscores saved next to the install by older versions over to the data dir, once
+    pub fn adopt_old_player_data(&self) {
+        let old = self.bundle_dir.join(OLD_PLAYER_DATA_PATH);
+        let new = self.player_data();
+        if new.exists() || !old.exists() || old == new { return }
+
+        let result = std::fs::create_dir_all(&self.data_dir).and_then(|_| std::fs::copy(&old, &new));
+        match result {
+            Ok(_) => eprintln!("{}: copied the player data over from {}", new.display(), old.display()),
+            Err(error) => eprintln!("{}: could not copy the player data over from {}: {error}", new.display(), old.display()),
+        }
+    }
+}
+
+//`cargo run` points at the crate, an installed game at the directory of the executable
+fn bundle_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+use crate::game::leaderboard::{Leaderboard, LeaderboardEntry, DEFAULT_PLAYER_NAME};
+use crate::game::world::{DeathCause, Side};
+
+/*
+    The player data file. A save is written to a temp file next to it and renamed over
+    the old one, so a crash halfway through never leaves a half written file behind,
+    and the file being replaced is kept as `<name>.bak`. A file that no longer parses is
+    moved aside to `<name>.corrupt` and the game carries on from the backup. A file from
+    a newer version of the game is read as far as it goes and never saved over.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct PlayerData {
+    //older layouts are upgraded by `migrate::player_data` before they get here
+    schema_version: u32,
+    highscores: BTreeMap<Difficulty, i32>,
+    //ranked per mode and difficulty, runs at different speeds are never in one list
+    leaderboards: BTreeMap<GameMode, BTreeMap<Difficulty, Leaderboard>>,
+    //the name last put on the leaderboard, offered again for the next run
+    player_name: String,
+    death_stats: DeathStats,
+    //set for a file from a newer version, writing it back would drop what this version does not know
+    #[serde(skip)]
+    read_only: bool,
+}
+
+//how every run so far ended, counted by what the snake died of
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DeathStats {
+    pub walls: BTreeMap<Side, u32>,
+    pub own_body: u32,
+    //the segment the snake ran into each time, counted from the head
+    pub own_body_segments: BTreeMap<usize, u32>,
+    pub obstacles: u32,
+    pub other_snakes: u32,
+    pub timeouts: u32,
+}
+
+impl DeathStats {
+    pub fn record(&mut self, cause: DeathCause) {
+        match cause {
+            DeathCause::Wall(side) => *self.walls.entry(side).or_default() += 1,
+            DeathCause::OwnBody(segment) => {
+                self.own_body += 1;
+                *self.own_body_segments.entry(segment).or_default() += 1;
+            }
+            DeathCause::Obstacle => self.obstacles += 1,
+            DeathCause::OtherSnake => self.other_snakes += 1,
+            DeathCause::Timeout => self.timeouts += 1,
+        }
+    }
+
+    pub fn wall_total(&self) -> u32 {
+        self.walls.values().sum()
+    }
+
+    pub fn total(&self) -> u32 {
+        self.wall_total() + self.own_body + self.obstacles + self.other_snakes + self.timeouts
+    }
+}
+
+impl Default for PlayerData {
+    fn default() -> Self {
+        PlayerData {
+            schema_version: migrate::player_data::CHAIN.len() as u32,
+            highscores: BTreeMap::new(),
+            leaderboards: BTreeMap::new(),
+            player_name: DEFAULT_PLAYER_NAME.to_string(),
+            death_stats: DeathStats::default(),
+            read_only: false,
+        }
+    }
+}
+
+impl PlayerData {
+    pub fn highscore(&self, difficulty: Difficulty) -> i32 {
+        self.highscores.get(&difficulty).copied().unwrap_or(0)
+    }
+
+    pub fn set_highscore(&mut self, difficulty: Difficulty, score: i32) {
+        self.highscores.insert(difficulty, score);
+    }
+
+    pub fn leaderboard(&self, mode: GameMode, difficulty: Difficulty) -> &[LeaderboardEntry] {
+        self.leaderboards.get(&mode)
+            .and_then(|leaderboards| leaderboards.get(&difficulty))
+            .map(Leaderboard::entries)
+            .unwrap_or_default()
+    }
+
+    //returns the rank the run got on the leaderboard of its mode and difficulty, if it made it on there
+    pub fn record_run(&mut self, mode: GameMode, entry: LeaderboardEntry) -> Option<usize> {
+        self.leaderboards.entry(mode).or_default()
+            .entry(entry.difficulty).or_default()
+            .insert(entry)
+    }
+
+    //gives a run already on the leaderboard the name typed in after it ended
+    pub fn rename_run(&mut self, mode: GameMode, difficulty: Difficulty, rank: usize, name: &str) {
+        let entry = self.leaderboards.get_mut(&mode)
+            .and_then(|leaderboards| leaderboards.get_mut(&difficulty))
+            .and_then(|leaderboard| leaderboard.entry_mut(rank));
+
+        if let Some(entry) = entry {
+            entry.name = name.to_string();
+        }
+    }
+
+    pub fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    pub fn set_player_name(&mut self, name: &str) {
+        self.player_name = name.to_string();
+    }
+
+    pub fn death_stats(&self) -> &DeathStats {
+        &self.death_stats
+    }
+
+    pub fn record_death(&mut self, cause: DeathCause) {
+        self.death_stats.record(cause);
+    }
+
+    /*
+        Never fails, a missing or broken file falls back to the backup and then to no scores
+        at all. Loaded while the app is being built, before there is a logger, so problems
+        are printed like the ones with the settings file.
+     */
+    pub fn load(path: &Path) -> Self {
+        match PlayerData::read(path) {
+            Ok(player_data) => player_data,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    eprintln!("{}: {error}", path.display());
+
+                    //keep the broken file for a look, the next save would overwrite it
+                    let corrupt = with_suffix(path, "corrupt");
+                    if fs::rename(path, &corrupt).is_ok() {
+                        eprintln!("{}: moved to {}", path.display(), corrupt.display());
+                    }
+                }
+                PlayerData::restore(path)
+            }
+        }
+    }
+
+    fn restore(path: &Path) -> Self {
+        let backup = with_suffix(path, "bak");
+        match PlayerData::read(&backup) {
+            Ok(player_data) => {
+                eprintln!("{}: restored from {}", path.display(), backup.display());
+                player_data
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => PlayerData::default(),
+            Err(error) => {
+                eprintln!("{}: {error}, starting without player data", backup.display());
+                PlayerData::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut value: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+        let version = migrate::migrate(&mut value, migrate::player_data::CHAIN)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut player_data: PlayerData = serde_json::from_value(value)?;
+        if version > migrate::player_data::CHAIN.len() as u32 {
+            eprintln!("{}: written by a newer version of the game (schema {version}), it will not be saved over", path.display());
+            player_data.read_only = true;
+        }
+        Ok(player_data)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::other("written by a newer version of the game, not saving over it"));
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let temp = with_suffix(path, "tmp");
+        let mut file = File::create(&temp)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+
+        //the last good save becomes the backup, then the new one takes its place in one step
+        if path.exists() {
+            fs::copy(path, with_suffix(path, "bak"))?;
+        }
+        fs::rename(&temp, path)
+    }
+}
+
+//`player_data.json` -> `player_data.json.bak`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+//where the player data is kept, without this resource nothing is written to disk
+pub struct SaveFile(pub PathBuf);
+
+fn write(save_file: &SaveFile, player_data: &PlayerData) {
+    if let Err(error) = player_data.save(&save_file.0) {
+        error!("{}: could not save player data: {error}", save_file.0.display());
+    }
+}
+
+/*
+    Both savers run in `CoreStage::Last`, after everything in the frame that could change
+    the data or ask to exit. Only a finished game changes the data, so `save_on_change`
+    is what saves after every run.
+ */
+pub fn save_on_change(
+    save_file: Option<Res<SaveFile>>,
+    player_data: Res<PlayerData>,
+) {
+    if !player_data.is_changed() || player_data.is_added() { return }
+
+    if let Some(save_file) = save_file {
+        write(&save_file, &player_data);
+    }
+}
+
+//the menu quit button, closing the window and Ctrl+C all end up as an `AppExit`
+pub fn save_on_exit(
+    app_exit_event: EventReader<AppExit>,
+    save_file: Option<Res<SaveFile>>,
+    player_data: Res<PlayerData>,
+) {
+    if app_exit_event.is_empty() { return }
+
+    if let Some(save_file) = save_file {
+        write(&save_file, &player_data);
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/*
+    Turns Ctrl+C into a regular `AppExit`, so the app shuts down through the same path as
+    the quit button and the data gets saved. A second Ctrl+C kills the process outright
+    in case the app is stuck and never gets to the exit.
+ */
+pub fn catch_interrupt() {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let result = ctrlc::set_handler(|| {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+        });
+
+        if let Err(error) = result {
+            eprintln!("could not catch Ctrl+C, it will quit without saving: {error}");
+        }
+    }
+}
+
+pub fn exit_on_interrupt(
+    mut app_exit_event: EventWriter<AppExit>,
+) {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        app_exit_event.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn save_goes_through_a_temp_file() {
+        let dir = TempDir::new("save");
+        let path = dir.join("player_data.json");
+
+        let mut player_data = PlayerData::default();
+        player_data.set_highscore(Difficulty::Hard, 12);
+        player_data.save(&path).unwrap();
+
+        assert!(!with_suffix(&path, "tmp").exists());
+        assert!(!with_suffix(&path, "bak").exists());
+        assert_eq!(PlayerData::load(&path).highscore(Difficulty::Hard), 12);
+    }
+
+    #[test]
+    fn save_keeps_the_last_one_as_backup() {
+        let dir = TempDir::new("backup");
+        let path = dir.join("player_data.json");
+
+        let mut player_data = PlayerData::default();
+        for score in [1, 2, 3] {
+            player_data.set_highscore(Difficulty::Normal, score);
+            player_data.save(&path).unwrap();
+        }
+
+        assert_eq!(PlayerData::read(&path).unwrap().highscore(Difficulty::Normal), 3);
+        assert_eq!(PlayerData::read(&with_suffix(&path, "bak")).unwrap().highscore(Difficulty::Normal), 2);
+    }
+
+    #[test]
+    fn broken_file_is_moved_aside_and_the_backup_loaded() {
+        let dir = TempDir::new("corrupt");
+        let path = dir.join("player_data.json");
+
+        let mut player_data = PlayerData::default();
+        player_data.set_highscore(Difficulty::Normal, 5);
+        player_data.save(&path).unwrap();
+        player_data.set_highscore(Difficulty::Normal, 6);
+        player_data.save(&path).unwrap();
+        fs::write(&path, "{ \"Highscores\": ").unwrap();
+
+        let player_data = PlayerData::load(&path);
+        assert_eq!(player_data.highscore(Difficulty::Normal), 5);
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(with_suffix(&path, "corrupt")).unwrap(), "{ \"Highscores\": ");
+    }
+
+    #[test]
+    fn nothing_to_load_is_no_scores() {
+        let dir = TempDir::new("missing");
+        let path = dir.join("player_data.json");
+
+        assert_eq!(PlayerData::load(&path).highscore(Difficulty::Normal), 0);
+        assert!(!with_suffix(&path, "corrupt").exists());
+    }
+
+    #[test]
+    fn old_highscore_loads_as_normal() {
+        let dir = TempDir::new("old-highscore");
+        let path = dir.join("player_data.json");
+        fs::write(&path, r#"{"Highscore":1}"#).unwrap();
+
+        let player_data = PlayerData::load(&path);
+        assert_eq!(player_data.highscore(Difficulty::Normal), 1);
+        assert_eq!(player_data.schema_version, migrate::player_data::CHAIN.len() as u32);
+    }
+
+    #[test]
+    fn newer_file_is_never_saved_over() {
+        let dir = TempDir::new("newer");
+        let path = dir.join("player_data.json");
+        let file = r#"{"SchemaVersion":99,"Highscores":{"Normal":3},"SomethingNew":true}"#;
+        fs::write(&path, file).unwrap();
+
+        let mut player_data = PlayerData::load(&path);
+        assert_eq!(player_data.highscore(Difficulty::Normal), 3);
+
+        player_data.set_highscore(Difficulty::Normal, 10);
+        assert!(player_data.save(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), file);
+    }
+}
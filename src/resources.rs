@@ -10,7 +10,7 @@ const REG_PATH: &str = "Fonts/GravityRegular.ttf";
 
 pub struct GameTextures {
     pub snake_atlas: Handle<TextureAtlas>,
-    pub checkerboard: Handle<Image>
+    pub checkerboard: Handle<TextureAtlas>
 }
 
 pub struct GameFonts {
@@ -21,15 +21,19 @@ pub struct GameFonts {
 pub fn create(
     mut commands: Commands, 
     mut app_state: ResMut<State<AppState>>,
+    config: Res<SnakeGameConfig>,
     asset_server: Res<AssetServer>, 
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
 
     let texture_handle: Handle<Image> = asset_server.load(TEXTURE_ATLAS_PATH);
     let texture_atlas: TextureAtlas = TextureAtlas::from_grid(texture_handle, Vec2::new(16.0, 16.), 4, 1);
+    //the checkerboard is cut into its dark and light square so the board can be built tile by tile
+    let checkerboard_handle: Handle<Image> = asset_server.load(CHECKERBOARD_PATH);
+    let checkerboard_atlas: TextureAtlas = TextureAtlas::from_grid(checkerboard_handle, Vec2::new(16.0, 16.), 2, 1);
     let textures: GameTextures = GameTextures {
         snake_atlas: texture_atlases.add(texture_atlas),
-        checkerboard: asset_server.load(CHECKERBOARD_PATH),
+        checkerboard: texture_atlases.add(checkerboard_atlas),
     };
     commands.insert_resource(textures);
 
@@ -40,5 +44,29 @@ pub fn create(
     };
     commands.insert_resource(fonts);
 
-    app_state.set(AppState::Menu).unwrap();
-}
\ No newline at end of file
+    app_state.set(first_state(&config)).unwrap();
+}
+
+//straight into the game when there is no menu to start from or a replay to watch
+fn first_state(config: &SnakeGameConfig) -> AppState {
+    if config.menus && config.replay.is_none() { AppState::Menu } else { AppState::Game }
+}
+
+//stand-in handles for when there is no asset server, nothing is ever drawn with them
+pub fn create_headless(
+    mut commands: Commands, 
+    mut app_state: ResMut<State<AppState>>,
+    config: Res<SnakeGameConfig>,
+) {
+    commands.insert_resource(GameTextures {
+        snake_atlas: Handle::default(),
+        checkerboard: Handle::default(),
+    });
+
+    commands.insert_resource(GameFonts {
+        bold: Handle::default(),
+        regular: Handle::default(),
+    });
+
+    app_state.set(first_state(&config)).unwrap();
+}
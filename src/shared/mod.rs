@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+
+pub mod text_input;
+
+//widgets any screen can use
+pub struct SharedPlugin;
+
+impl Plugin for SharedPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<text_input::TextSubmitted>()
+            .add_system(text_input::text_input);
+    }
+}
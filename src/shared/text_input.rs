@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+/*
+    A one line text field for the keyboard. Put it on an entity with a `Text` of three
+    sections, the text before the cursor, the cursor and the text after it, and
+    `text_input` keeps them in step with what is typed. Enter sends `TextSubmitted`.
+    A new field takes nothing until every key has been let go, so a key still held from
+    before it showed up, a turn at the moment the snake died, does not type into it.
+ */
+#[derive(Component, Clone, Debug)]
+pub struct TextInput {
+    value: Vec<char>,
+    //between `value[cursor - 1]` and `value[cursor]`
+    cursor: usize,
+    max_length: usize,
+    //true until the first frame with no key held
+    waiting: bool,
+}
+
+//events
+pub struct TextSubmitted {
+    pub entity: Entity,
+    pub value: String,
+}
+
+pub const CURSOR: &str = "|";
+
+impl TextInput {
+    //starts out holding `value` with the cursor at the end
+    pub fn new(value: &str, max_length: usize) -> Self {
+        let value: Vec<char> = value.chars().take(max_length).collect();
+        TextInput {
+            cursor: value.len(),
+            value,
+            max_length,
+            waiting: true,
+        }
+    }
+
+    pub fn value(&self) -> String {
+        self.value.iter().collect()
+    }
+
+    //returns false when the field is full or the character cannot be typed
+    pub fn insert(&mut self, character: char) -> bool {
+        if character.is_control() || self.value.len() >= self.max_length {
+            return false;
+        }
+
+        self.value.insert(self.cursor, character);
+        self.cursor += 1;
+        true
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.value.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.value.len() {
+            self.value.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.value.len());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.value.len();
+    }
+
+    //the text around the cursor, for the first and last of the three sections
+    pub fn split(&self) -> (String, String) {
+        (self.value[..self.cursor].iter().collect(), self.value[self.cursor..].iter().collect())
+    }
+}
+
+//three sections in `style`, the cursor in `cursor_color`
+pub fn text_input_sections(input: &TextInput, style: &TextStyle, cursor_color: Color) -> Vec<TextSection> {
+    let (before, after) = input.split();
+
+    vec![
+        TextSection { value: before, style: style.clone() },
+        TextSection { value: CURSOR.to_string(), style: TextStyle { color: cursor_color, ..style.clone() } },
+        TextSection { value: after, style: style.clone() },
+    ]
+}
+
+/*
+    Feeds the keyboard into every text field there is, screens only ever show one at a
+    time. Characters come from `ReceivedCharacter` so the keyboard layout and shift are
+    respected, editing keys from `Input<KeyCode>`.
+ */
+pub fn text_input(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    input: Res<Input<KeyCode>>,
+    mut query: Query<(Entity, &mut TextInput, &mut Text)>,
+    mut submitted: EventWriter<TextSubmitted>,
+) {
+    let characters: Vec<char> = received_characters.iter().map(|event| event.char).collect();
+
+    for (entity, mut text_input, mut text) in query.iter_mut() {
+        if text_input.waiting {
+            if input.get_pressed().next().is_some() { continue }
+            text_input.waiting = false;
+        }
+
+        for character in characters.iter() {
+            text_input.insert(*character);
+        }
+
+        if input.just_pressed(KeyCode::Back) { text_input.backspace() }
+        if input.just_pressed(KeyCode::Delete) { text_input.delete() }
+        if input.just_pressed(KeyCode::Left) { text_input.move_left() }
+        if input.just_pressed(KeyCode::Right) { text_input.move_right() }
+        if input.just_pressed(KeyCode::Home) { text_input.home() }
+        if input.just_pressed(KeyCode::End) { text_input.end() }
+
+        if input.just_pressed(KeyCode::Return) || input.just_pressed(KeyCode::NumpadEnter) {
+            submitted.send(TextSubmitted { entity, value: text_input.value() });
+        }
+
+        if text_input.is_changed() && text.sections.len() == 3 {
+            let (before, after) = text_input.split();
+            text.sections[0].value = before;
+            text.sections[2].value = after;
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/*
+    An empty directory of its own for a test to write files into, removed again with
+    everything in it when dropped. Shared with the integration tests, which pull this
+    file in by path, so it only uses std.
+ */
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    //`name` keeps the tests running side by side apart, the process id separate test runs
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("snake-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use bevy::ecs::event::Events;
+use bevy::prelude::*;
+use snake::*;
+use snake::Direction;
+
+#[path = "../src/temp_dir.rs"]
+mod temp_dir;
+
+use temp_dir::TempDir;
+
+//every `KillSnake` the game saw, in order
+#[derive(Default)]
+struct Kills(Vec<KillSnake>);
+
+fn watch_kills(mut events: EventReader<KillSnake>, mut kills: ResMut<Kills>) {
+    kills.0.extend(events.iter().copied());
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugin(HeadlessGamePlugin::new())
+        .init_resource::<Kills>()
+        .add_system(watch_kills.after(TickPhase::Collide));
+    app
+}
+
+fn game_state(app: &App) -> GameState {
+    app.world.resource::<State<GameState>>().current().clone()
+}
+
+//runs frames in real time, the ticks go by the clock, until `done` or the time is up
+fn run_until(app: &mut App, seconds: u64, done: impl Fn(&App) -> bool) {
+    let start = Instant::now();
+    while !done(app) {
+        assert!(start.elapsed() < Duration::from_secs(seconds), "timed out in {:?}", game_state(app));
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn snake_left_alone_dies_on_the_left_wall() {
+    let mut app = app();
+    run_until(&mut app, 20, |app| game_state(app) == GameState::Dead);
+
+    let world = app.world.resource::<SnakeWorld>();
+    assert!(!world.is_alive());
+    assert_eq!(world.head().x, 0);
+
+    //the snake starts heading left and nothing turns it
+    let kills = &app.world.resource::<Kills>().0;
+    assert_eq!(kills.len(), 1);
+    assert_eq!(kills[0].cause, DeathCause::Wall(Side::Left));
+    assert_eq!(kills[0].cell, world.head());
+    assert_eq!(kills[0].tick, world.tick());
+}
+
+#[test]
+fn host_can_kill_the_snake_with_a_cause() {
+    let mut app = app();
+    run_until(&mut app, 5, |app| app.world.get_resource::<SnakeWorld>().is_some() && game_state(app) == GameState::InGame);
+
+    let head = app.world.resource::<SnakeWorld>().head();
+    app.world.resource_mut::<Events<KillSnake>>().send(KillSnake {
+        cause: DeathCause::Timeout,
+        cell: head,
+        tick: 0,
+    });
+    run_until(&mut app, 5, |app| game_state(app) == GameState::Dead);
+
+    assert_eq!(app.world.resource::<LastRun>().death.map(|kill| kill.cause), Some(DeathCause::Timeout));
+    assert_eq!(app.world.resource::<PlayerData>().death_stats().timeouts, 1);
+}
+
+#[test]
+fn kill_sent_while_paused_is_dropped() {
+    let mut app = app();
+    run_until(&mut app, 5, |app| app.world.get_resource::<SnakeWorld>().is_some() && game_state(app) == GameState::InGame);
+
+    app.world.resource_mut::<State<GameState>>().push(GameState::Paused).unwrap();
+    run_until(&mut app, 5, |app| game_state(app) == GameState::Paused);
+
+    let head = app.world.resource::<SnakeWorld>().head();
+    app.world.resource_mut::<Events<KillSnake>>().send(KillSnake {
+        cause: DeathCause::Timeout,
+        cell: head,
+        tick: 0,
+    });
+    for _ in 0..5 {
+        app.update();
+    }
+
+    assert_eq!(game_state(&app), GameState::Paused);
+    assert!(app.world.resource::<SnakeWorld>().is_alive());
+    assert_eq!(app.world.resource::<PlayerData>().death_stats().timeouts, 0);
+}
+
+#[test]
+#[should_panic(expected = "at least 3x3 tiles")]
+fn arena_smaller_than_the_snake_is_refused() {
+    App::new()
+        .add_plugins(MinimalPlugins)
+        .add_plugin(SnakeGamePlugin::new().arena_size(2, 10).headless(true).with_menus(false).save_path(None));
+}
+
+//steers the snake to the food until it has eaten three pieces, then lets it run into a wall
+fn chase_food(world: Option<Res<SnakeWorld>>, mut head_query: Query<&mut SnakeHead>) {
+    let world = match world {
+        Some(world) => world,
+        None => return,
+    };
+    let (mut snake_head, food) = match (head_query.iter_mut().next(), world.food().first()) {
+        (Some(snake_head), Some(food)) => (snake_head, *food),
+        _ => return,
+    };
+    if snake_head.score >= 3 { return }
+
+    let head = world.head();
+    let wanted = [
+        (food.x < head.x, Direction::Left),
+        (food.x > head.x, Direction::Right),
+        (food.y > head.y, Direction::Up),
+        (food.y < head.y, Direction::Down),
+    ];
+    //food behind the snake is reached by turning off to the side first
+    let turn = wanted.iter()
+        .filter(|(wanted, _)| *wanted)
+        .map(|(_, turn)| *turn)
+        .find(|turn| *turn != world.direction().opposite())
+        .unwrap_or(match world.direction() {
+            Direction::Left | Direction::Right => Direction::Up,
+            Direction::Up | Direction::Down => Direction::Left,
+        });
+
+    if turn != world.direction() {
+        snake_head.queue_turn(turn, &world);
+    }
+}
+
+#[test]
+fn recorded_run_plays_back_to_its_result() {
+    let dir = TempDir::new("recorded-run");
+
+    let mut app = App::new();
+    app.insert_resource(LaunchArgs {
+            seed: Some(7),
+            data_dir: Some(dir.to_path_buf()),
+            tick_rate: Some(60.),
+            headless: true,
+            skip_menu: true,
+            ..Default::default()
+        })
+        .add_plugin(HeadlessGamePlugin::new())
+        .add_system(chase_food.before(TickPhase::Input));
+    run_until(&mut app, 30, |app| game_state(app) == GameState::Dead);
+
+    let file = app.world.resource::<ReplayRecorder>().last_file.clone().expect("the run was not saved");
+    let replay = Replay::load(&app.world.resource::<ReplayDir>().0.join(file)).unwrap();
+    let result = replay.result.unwrap();
+    assert_eq!(result.outcome, RunOutcome::Died);
+    assert!(result.score >= 3);
+
+    let (world, _, score) = replay.play_to(replay.end_tick());
+    assert!(!world.is_alive());
+    assert_eq!(world.tick(), result.ticks);
+    assert_eq!(score, result.score);
+    assert_eq!(world.length(), result.length);
+}